@@ -1,5 +1,29 @@
 //! Data structures for sharing data between multiple threads.
 
+mod arc;
+mod condvar;
+pub mod epoch;
+mod futex;
+mod lazy_lock;
+pub mod mpsc;
 mod mutex;
+mod once_lock;
+mod poison;
+mod queue;
+mod rwlock;
+pub mod spsc;
+mod treiber_stack;
 
-pub use mutex::Mutex;
+pub use arc::{Arc, Weak};
+pub use condvar::{Condvar, WaitTimeoutResult};
+pub use lazy_lock::LazyLock;
+pub use mutex::{Mutex, MutexGuard, PoisoningMutex, PoisoningMutexGuard};
+pub use once_lock::OnceLock;
+pub use poison::{LockResult, PoisonError, TryLockError, TryLockResult};
+pub use queue::Queue;
+pub use rwlock::{
+    PoisoningRwLock, PoisoningRwLockReadGuard, PoisoningRwLockUpgradableReadGuard,
+    PoisoningRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockUpgradableReadGuard,
+    RwLockWriteGuard,
+};
+pub use treiber_stack::TreiberStack;
@@ -0,0 +1,483 @@
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+/// A soft limit on the number of references that can be handed out. Going over this limit
+/// aborts the process, since the counts would otherwise overflow and free the value while it is
+/// still in use.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// The value of the weak count while it is locked by [`Arc::get_mut`] to check for uniqueness.
+const WEAK_LOCKED: usize = usize::MAX;
+
+/// The inner representation of `Arc<T>` that gets allocated on the heap.
+struct ArcInner<T> {
+    /// The value referenced by our smart pointer. It is dropped as soon as the last `Arc` goes
+    /// away, even if some `Weak` still keeps the allocation.
+    value: ManuallyDrop<T>,
+    /// The number of `Arc` that have been handed out.
+    strong: AtomicUsize,
+    /// The number of `Weak` that have been handed out, plus one that is collectively held by all
+    /// the `Arc` for as long as there is one.
+    weak: AtomicUsize,
+}
+
+/// A thread-safe reference-counted smart pointer that deallocates the inner value once there's
+/// no reference pointing to the inner value.
+#[derive(Debug)]
+pub struct Arc<T> {
+    inner: NonNull<ArcInner<T>>,
+    marker: PhantomData<ArcInner<T>>,
+}
+
+// SAFETY: An `Arc` gives out shared references to `T` on multiple threads, so `T` must be
+// [`Sync`]. The last `Arc` to go away drops `T`, which can happen on any thread, so `T` must also
+// be [`Send`].
+unsafe impl<T> Send for Arc<T> where T: Send + Sync {}
+
+// SAFETY: Sharing an `Arc` allows cloning it on another thread, which is the same as sending it.
+unsafe impl<T> Sync for Arc<T> where T: Send + Sync {}
+
+impl<T> Arc<T> {
+    /// Allocate the given value onto the heap and return a thread-safe reference-counted smart
+    /// pointer to it.
+    pub fn new(value: T) -> Self {
+        let inner = Box::new(ArcInner {
+            value: ManuallyDrop::new(value),
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+        });
+        Self {
+            // SAFETY: `Box::into_raw` does not give a null pointer.
+            inner: unsafe { NonNull::new_unchecked(Box::into_raw(inner)) },
+            marker: PhantomData,
+        }
+    }
+
+    /// Create a `Weak` pointer to the value of this `Arc`.
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let inner = this.inner();
+        let mut weak = inner.weak.load(Ordering::Relaxed);
+        loop {
+            // Wait for `get_mut` to finish checking whether it has the only reference.
+            if weak == WEAK_LOCKED {
+                std::hint::spin_loop();
+                weak = inner.weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if weak > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire synchronizes with the Release store in `is_unique`, so that the strong count
+            // observed by `is_unique` is not affected by what happens after this downgrade.
+            match inner.weak.compare_exchange_weak(
+                weak,
+                weak + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Weak { inner: this.inner },
+                Err(old) => weak = old,
+            }
+        }
+    }
+
+    /// Get the number of `Arc` pointing to the value of this `Arc`.
+    ///
+    /// Another thread can change the count at any time, so the result should not be relied upon
+    /// for correctness.
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Acquire)
+    }
+
+    /// Get the number of `Weak` pointing to the value of this `Arc`.
+    ///
+    /// Another thread can change the count at any time, so the result should not be relied upon
+    /// for correctness.
+    pub fn weak_count(this: &Self) -> usize {
+        match this.inner().weak.load(Ordering::Acquire) {
+            // The count is only locked when there was no `Weak`.
+            WEAK_LOCKED => 0,
+            weak => weak - 1,
+        }
+    }
+
+    /// Get a mutable reference to the inner value if there is no other `Arc` or `Weak` pointing
+    /// to it.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if this.is_unique() {
+            // SAFETY: We have the only pointer to the value, and no other can be created while we
+            // are holding a mutable reference to it.
+            Some(unsafe { &mut (*this.inner.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    /// Get a mutable reference to the inner value, cloning it into a new allocation first if
+    /// there are other `Arc` pointing to it.
+    ///
+    /// If this is the only `Arc` but some `Weak` are pointing to the value, the value is moved
+    /// into a new allocation instead, and the `Weak` can no longer be upgraded.
+    pub fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+    {
+        // Acquire synchronizes with the Release decrement in `drop` of the other `Arc`, so that
+        // their accesses to the value happen before ours.
+        if this
+            .inner()
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // There are other `Arc`, so we have to clone the value.
+            *this = Arc::new((**this).clone());
+        } else if this.inner().weak.load(Ordering::Relaxed) != 1 {
+            // We were the only `Arc` and the strong count is now zero, so no `Weak` can upgrade
+            // anymore. Move the value out and let the `Weak` keep the old allocation.
+            let old = Weak { inner: this.inner };
+            // SAFETY: The strong count is zero, nothing else can access the value, and it will
+            // not be dropped again.
+            let value = unsafe { ManuallyDrop::take(&mut (*this.inner.as_ptr()).value) };
+            // SAFETY: The old `Arc` must not be dropped since its strong count is already zero,
+            // the weak reference it was holding has been transferred to `old`.
+            unsafe { std::ptr::write(this, Arc::new(value)) };
+            drop(old);
+        } else {
+            // We were the only reference of either kind, nobody could have created another one
+            // in the meantime, so we can simply restore the strong count.
+            this.inner().strong.store(1, Ordering::Release);
+        }
+        // SAFETY: Either we have just created a new allocation, or we know that there was no other
+        // pointer to the value.
+        unsafe { &mut (*this.inner.as_ptr()).value }
+    }
+
+    /// Return whether the two `Arc` point to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner == other.inner
+    }
+
+    /// Return the inner value if this is the only `Arc` pointing to it, otherwise, return the
+    /// `Arc` back.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if this
+            .inner()
+            .strong
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        // Synchronizes with the Release decrement in `drop` of the other `Arc`.
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: The strong count is zero, nothing else can access the value, and it will not
+        // be dropped again.
+        let value = unsafe { ManuallyDrop::take(&mut (*this.inner.as_ptr()).value) };
+        // Release the weak reference that is held by all the `Arc` without running our `drop`.
+        let this = ManuallyDrop::new(this);
+        drop(Weak { inner: this.inner });
+        Ok(value)
+    }
+
+    /// Check whether this is the only `Arc` or `Weak` pointing to the value.
+    fn is_unique(&mut self) -> bool {
+        // Lock the weak count, so that no `Weak` can be created by another `Arc` while we are
+        // checking the strong count.
+        if self
+            .inner()
+            .weak
+            .compare_exchange(1, WEAK_LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            // Acquire synchronizes with the Release decrement in `drop` of the other `Arc`, so
+            // that their accesses to the value happen before ours.
+            let unique = self.inner().strong.load(Ordering::Acquire) == 1;
+            // Release synchronizes with the Acquire increment in `downgrade`.
+            self.inner().weak.store(1, Ordering::Release);
+            unique
+        } else {
+            false
+        }
+    }
+
+    fn inner(&self) -> &ArcInner<T> {
+        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Arc`
+        // and the last `Weak` go away, dereference the shared pointer here is fine since we are
+        // having an `Arc`.
+        unsafe { self.inner.as_ref() }
+    }
+}
+
+impl<T> Deref for Arc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // The value is only dropped once the last `Arc` goes away.
+        &self.inner().value
+    }
+}
+
+impl<T> Clone for Arc<T> {
+    fn clone(&self) -> Self {
+        // Relaxed is enough since a new reference can only be created from an existing one, which
+        // already keeps the value alive.
+        let strong = self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        if strong > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        Self {
+            inner: self.inner,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Arc<T> {
+    fn drop(&mut self) {
+        // Release ensures that every access to the value through this `Arc` happens before the
+        // value is dropped by whichever thread drops the last `Arc`.
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Acquire synchronizes with the Release decrements of all the other `Arc`, so that their
+        // accesses to the value happen before we drop it.
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: We are dropping the only `Arc` left, after being dropped, there is no more
+        // reference to `T`. Hence, dropping the value is safe. `Weak` can no longer be upgraded
+        // since the strong count has reached zero.
+        unsafe { ManuallyDrop::drop(&mut (*self.inner.as_ptr()).value) };
+        // Release the weak reference that is held by all the `Arc`, this deallocates the memory
+        // if there is no `Weak` left.
+        drop(Weak { inner: self.inner });
+    }
+}
+
+/// A non-owning pointer to the value of an `Arc`.
+///
+/// A `Weak` does not keep the value alive, it must be upgraded to an `Arc` before the value can be
+/// accessed, which fails if the value has already been dropped.
+#[derive(Debug)]
+pub struct Weak<T> {
+    inner: NonNull<ArcInner<T>>,
+}
+
+// SAFETY: A `Weak` can be upgraded into an `Arc` on any thread, so it has the same requirements.
+unsafe impl<T> Send for Weak<T> where T: Send + Sync {}
+
+// SAFETY: A `Weak` can be upgraded into an `Arc` on any thread, so it has the same requirements.
+unsafe impl<T> Sync for Weak<T> where T: Send + Sync {}
+
+impl<T> Weak<T> {
+    /// Attempt to get an `Arc` to the value, returns `None` if the value has already been dropped.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        let inner = self.inner();
+        let mut strong = inner.strong.load(Ordering::Relaxed);
+        loop {
+            if strong == 0 {
+                return None;
+            }
+            if strong > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire synchronizes with the Release store in `make_mut`.
+            match inner.strong.compare_exchange_weak(
+                strong,
+                strong + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(Arc {
+                        inner: self.inner,
+                        marker: PhantomData,
+                    })
+                }
+                Err(old) => strong = old,
+            }
+        }
+    }
+
+    /// Get the number of `Arc` pointing to the value.
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.load(Ordering::Acquire)
+    }
+
+    /// Get the number of `Weak` pointing to the value, returns 0 if the value has already been
+    /// dropped.
+    pub fn weak_count(&self) -> usize {
+        let inner = self.inner();
+        let weak = inner.weak.load(Ordering::Acquire);
+        if inner.strong.load(Ordering::Acquire) == 0 {
+            0
+        } else {
+            // The weak count cannot be locked since there is at least one `Weak`.
+            weak - 1
+        }
+    }
+
+    fn inner(&self) -> &ArcInner<T> {
+        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Arc`
+        // and the last `Weak` go away, dereference the shared pointer here is fine since we are
+        // having a `Weak`.
+        unsafe { self.inner.as_ref() }
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        // The weak count cannot be locked since there is at least one `Weak`.
+        let weak = self.inner().weak.fetch_add(1, Ordering::Relaxed);
+        if weak > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        Self { inner: self.inner }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        if self.inner().weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Acquire synchronizes with the Release decrements of all the other `Weak`.
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: We are dropping the last weak reference, which is only possible once every
+        // `Arc` has gone away and the value has been dropped. Hence, deallocating the heap memory
+        // is safe. The value is wrapped in `ManuallyDrop` so it is not dropped twice.
+        drop(unsafe { Box::from_raw(self.inner.as_ptr()) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Increment the shared counter when dropped.
+    #[derive(Clone)]
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn counts_track_clones_and_drops() {
+        let arc = Arc::new(42);
+        let arc2 = arc.clone();
+        let weak = Arc::downgrade(&arc);
+        assert_eq!(Arc::strong_count(&arc), 2);
+        assert_eq!(Arc::weak_count(&arc), 1);
+        assert_eq!(weak.strong_count(), 2);
+        assert_eq!(weak.weak_count(), 1);
+
+        drop(arc2);
+        assert_eq!(*weak.upgrade().unwrap(), 42);
+        drop(arc);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.strong_count(), 0);
+        assert_eq!(weak.weak_count(), 0);
+    }
+
+    #[test]
+    fn value_is_dropped_once_by_last_arc() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let arc = Arc::new(DropCounter(drops.clone()));
+        let weak = Arc::downgrade(&arc);
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let arc = arc.clone();
+                std::thread::spawn(move || drop(arc))
+            })
+            .collect();
+        drop(arc);
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn get_mut_requires_unique_ownership() {
+        let mut arc = Arc::new(1);
+        *Arc::get_mut(&mut arc).unwrap() += 1;
+        assert_eq!(*arc, 2);
+
+        let arc2 = arc.clone();
+        assert!(Arc::get_mut(&mut arc).is_none());
+        drop(arc2);
+
+        let weak = Arc::downgrade(&arc);
+        assert!(Arc::get_mut(&mut arc).is_none());
+        drop(weak);
+        assert!(Arc::get_mut(&mut arc).is_some());
+    }
+
+    #[test]
+    fn make_mut_clones_when_shared() {
+        let mut arc = Arc::new(1);
+        *Arc::make_mut(&mut arc) += 1;
+        assert_eq!(Arc::strong_count(&arc), 1);
+
+        let other = arc.clone();
+        *Arc::make_mut(&mut arc) += 1;
+        assert_eq!(*arc, 3);
+        assert_eq!(*other, 2);
+        assert_eq!(Arc::strong_count(&other), 1);
+
+        let weak = Arc::downgrade(&arc);
+        *Arc::make_mut(&mut arc) += 1;
+        assert_eq!(*arc, 4);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(Arc::weak_count(&arc), 0);
+    }
+
+    #[test]
+    fn try_unwrap_requires_single_arc() {
+        let arc = Arc::new(String::from("foo"));
+        let other = arc.clone();
+        let arc = Arc::try_unwrap(arc).unwrap_err();
+        drop(other);
+
+        let weak = Arc::downgrade(&arc);
+        assert_eq!(Arc::try_unwrap(arc).unwrap(), "foo");
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn concurrent_downgrade_and_upgrade() {
+        const N_THREADS: usize = 8;
+        const N_ITER: usize = 1000;
+
+        let arc = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                let arc = arc.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..N_ITER {
+                        let weak = Arc::downgrade(&arc);
+                        weak.upgrade().unwrap().fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(arc.load(Ordering::Relaxed), N_THREADS * N_ITER);
+        assert_eq!(Arc::strong_count(&arc), 1);
+        assert_eq!(Arc::weak_count(&arc), 0);
+    }
+}
@@ -0,0 +1,219 @@
+use super::futex;
+use super::MutexGuard;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// A condition variable, which lets threads wait for a condition on the data protected by a
+/// [`Mutex`](super::Mutex).
+///
+/// Waiting releases the lock and blocks the thread until it is notified, then reacquires the lock
+/// before returning. Waits can return spuriously, so the condition must always be checked again
+/// after waking up, which is what [`Condvar::wait_while`] does.
+#[derive(Debug)]
+pub struct Condvar {
+    /// Incremented on every notification. A waiter reads it before releasing the lock, so a
+    /// notification sent after the lock is released always changes the value it waits on.
+    counter: AtomicU32,
+}
+
+impl Condvar {
+    /// Create a condition variable.
+    pub const fn new() -> Self {
+        Self {
+            counter: AtomicU32::new(0),
+        }
+    }
+
+    /// Release the lock held by `guard`, block until this condition variable is notified, then
+    /// reacquire the lock.
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex;
+        // The counter is read while holding the lock. A notifying thread changes the data
+        // protected by the mutex before notifying, so it can only increment the counter after we
+        // release the lock below.
+        let counter = self.counter.load(Ordering::Relaxed);
+        drop(guard);
+        futex::wait(&self.counter, counter);
+        mutex.lock()
+    }
+
+    /// Block until `condition` returns `false`, the condition is checked while holding the lock
+    /// every time the thread wakes up.
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Same as [`Condvar::wait`], but gives up waiting for a notification after `timeout` has
+    /// elapsed. The lock is reacquired in both cases.
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let mutex = guard.mutex;
+        let counter = self.counter.load(Ordering::Relaxed);
+        drop(guard);
+        let woken = futex::wait_timeout(&self.counter, counter, timeout);
+        (mutex.lock(), WaitTimeoutResult(!woken))
+    }
+
+    /// Same as [`Condvar::wait_while`], but gives up waiting after `timeout` has elapsed. The
+    /// returned [`WaitTimeoutResult`] tells whether the condition still holds because of the
+    /// timeout.
+    pub fn wait_timeout_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        timeout: Duration,
+        mut condition: F,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        // A timeout too large to be represented as a deadline never elapses.
+        let deadline = Instant::now().checked_add(timeout);
+        while condition(&mut *guard) {
+            let deadline = match deadline {
+                Some(deadline) => deadline,
+                None => {
+                    guard = self.wait(guard);
+                    continue;
+                }
+            };
+            let timeout = match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if !timeout.is_zero() => timeout,
+                _ => return (guard, WaitTimeoutResult(true)),
+            };
+            guard = self.wait_timeout(guard, timeout).0;
+        }
+        (guard, WaitTimeoutResult(false))
+    }
+
+    /// Wake up one thread blocked on this condition variable.
+    pub fn notify_one(&self) {
+        self.counter.fetch_add(1, Ordering::Relaxed);
+        futex::wake_one(&self.counter);
+    }
+
+    /// Wake up all threads blocked on this condition variable.
+    pub fn notify_all(&self) {
+        self.counter.fetch_add(1, Ordering::Relaxed);
+        futex::wake_all(&self.counter);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a timed wait on a [`Condvar`] returned because the timeout elapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Return `true` if the wait gave up because the timeout elapsed.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::{Arc, Mutex};
+    use std::thread;
+
+    #[test]
+    fn producer_wakes_consumer() {
+        const N_ITEMS: usize = 1000;
+
+        let pair = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
+        let consumer = {
+            let pair = pair.clone();
+            thread::spawn(move || {
+                let (items, condvar) = &*pair;
+                let mut received = 0;
+                while received < N_ITEMS {
+                    let mut items = condvar.wait_while(items.lock(), |items| items.is_empty());
+                    received += items.drain(..).count();
+                }
+                received
+            })
+        };
+
+        let (items, condvar) = &*pair;
+        for i in 0..N_ITEMS {
+            items.lock().push(i);
+            condvar.notify_one();
+        }
+        assert_eq!(consumer.join().unwrap(), N_ITEMS);
+    }
+
+    #[test]
+    fn notify_all_wakes_every_waiter() {
+        const N_THREADS: usize = 8;
+
+        let pair = Arc::new((Mutex::new(false), Condvar::new()));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                let pair = pair.clone();
+                thread::spawn(move || {
+                    let (ready, condvar) = &*pair;
+                    let _guard = condvar.wait_while(ready.lock(), |ready| !*ready);
+                })
+            })
+            .collect();
+
+        let (ready, condvar) = &*pair;
+        *ready.lock() = true;
+        condvar.notify_all();
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn wait_timeout_elapses_without_notification() {
+        let mutex = Mutex::new(0);
+        let condvar = Condvar::new();
+        let (guard, res) = condvar.wait_timeout(mutex.lock(), Duration::from_millis(10));
+        assert!(res.timed_out());
+        drop(guard);
+
+        let (guard, res) =
+            condvar.wait_timeout_while(mutex.lock(), Duration::from_millis(10), |v| *v == 0);
+        assert!(res.timed_out());
+        assert_eq!(*guard, 0);
+    }
+
+    #[test]
+    fn wait_timeout_while_accepts_unrepresentable_deadline() {
+        let pair = Arc::new((Mutex::new(false), Condvar::new()));
+        let notifier = {
+            let pair = pair.clone();
+            thread::spawn(move || {
+                let (ready, condvar) = &*pair;
+                *ready.lock() = true;
+                condvar.notify_one();
+            })
+        };
+
+        let (ready, condvar) = &*pair;
+        let (guard, res) = condvar.wait_timeout_while(ready.lock(), Duration::MAX, |ready| !*ready);
+        assert!(!res.timed_out());
+        assert!(*guard);
+        drop(guard);
+        notifier.join().unwrap();
+    }
+}
@@ -0,0 +1,426 @@
+//! Epoch-based memory reclamation for lock-free data structures.
+//!
+//! A lock-free structure can not free a node as soon as it unlinks it, since other threads might
+//! still be reading it. Instead, threads [`pin`] themselves before accessing the structure, and
+//! unlinked nodes are handed to [`Guard::defer_destroy`]. A global epoch is advanced once every
+//! pinned thread has observed its current value, and a node retired in some epoch is freed once
+//! the global epoch is far enough ahead that no thread pinned at the time can still be pinned.
+//!
+//! This is what [`Queue`](super::Queue) and [`TreiberStack`](super::TreiberStack) build on. A
+//! lock-free structure using it follows the same pattern:
+//!
+//! ```
+//! use rusty_crust::atomics::epoch;
+//! use std::ptr;
+//! use std::sync::atomic::{AtomicPtr, Ordering};
+//!
+//! let slot = AtomicPtr::new(Box::into_raw(Box::new(1)));
+//!
+//! let guard = epoch::pin();
+//! let old = slot.swap(Box::into_raw(Box::new(2)), Ordering::AcqRel);
+//! // SAFETY: `old` came from `Box::into_raw` and is no longer reachable from `slot`, threads
+//! // that loaded it before the swap keep it alive by being pinned.
+//! unsafe { guard.defer_destroy(old) };
+//! drop(guard);
+//!
+//! // SAFETY: We own the last value.
+//! drop(unsafe { Box::from_raw(slot.swap(ptr::null_mut(), Ordering::AcqRel)) });
+//! ```
+
+use crate::cell::Cell;
+use crate::refcell::RefCell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::atomic::{self, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+/// The number of deferred functions a thread collects before trying to advance the epoch.
+const COLLECT_THRESHOLD: usize = 64;
+
+/// The number of epochs that must pass after a node is retired before it can be freed.
+///
+/// A thread that retires a node is pinned at some epoch `e`, so the global epoch is either `e` or
+/// `e + 1`. Threads that could have loaded the node before it was unlinked are therefore pinned at
+/// `e + 1` at the latest, and they are all unpinned once the global epoch reaches `e + 3`.
+const EPOCHS_BEFORE_FREE: usize = 3;
+
+/// The global epoch.
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+/// The records of all the threads that have ever been pinned.
+static PARTICIPANTS: AtomicPtr<Participant> = AtomicPtr::new(ptr::null_mut());
+
+/// Deferred functions left behind by threads that exited before they could run them.
+static ORPHANS: AtomicPtr<Orphan> = AtomicPtr::new(ptr::null_mut());
+
+/// The shared record of a thread taking part in the reclamation.
+///
+/// Records are never freed, a record released by an exiting thread is reused by the next thread
+/// that needs one.
+#[derive(Debug)]
+struct Participant {
+    /// `0` if the thread is not pinned, otherwise the epoch it is pinned at, shifted left by one
+    /// with the lowest bit set.
+    state: AtomicUsize,
+    in_use: AtomicBool,
+    next: *mut Participant,
+}
+
+/// A function to be run once no thread can be accessing the memory it frees.
+#[derive(Debug)]
+struct Deferred {
+    ptr: *mut (),
+    call: unsafe fn(*mut ()),
+}
+
+// SAFETY: Callers of `Guard::defer_destroy` guarantee that the pointed-to value can be dropped on
+// any thread.
+unsafe impl Send for Deferred {}
+
+/// A batch of deferred functions that can run once enough epochs have passed.
+#[derive(Debug)]
+struct Bag {
+    /// The latest epoch at which one of the functions was deferred.
+    epoch: usize,
+    deferred: Vec<Deferred>,
+}
+
+impl Bag {
+    fn is_ready(&self, global: usize) -> bool {
+        global.wrapping_sub(self.epoch) >= EPOCHS_BEFORE_FREE
+    }
+
+    fn run(self) {
+        for deferred in self.deferred {
+            // SAFETY: Enough epochs have passed since the function was deferred.
+            unsafe { (deferred.call)(deferred.ptr) };
+        }
+    }
+}
+
+/// A bag left behind by an exited thread.
+#[derive(Debug)]
+struct Orphan {
+    bag: Bag,
+    next: *mut Orphan,
+}
+
+/// The state of the current thread.
+#[derive(Debug)]
+struct Local {
+    participant: &'static Participant,
+    /// The number of live guards, a thread is pinned while there is at least one.
+    guards: Cell<usize>,
+    /// The functions deferred since the last collection.
+    bag: RefCell<Vec<Deferred>>,
+    /// The epoch the thread was pinned at when it last deferred a function.
+    bag_epoch: Cell<usize>,
+    /// Bags waiting for enough epochs to pass, oldest first.
+    sealed: RefCell<VecDeque<Bag>>,
+}
+
+thread_local! {
+    static LOCAL: Local = Local::register();
+}
+
+/// A guard that keeps the current thread pinned, created by [`pin`].
+///
+/// Memory that was reachable from a lock-free structure when the guard was created is not freed
+/// until the guard goes away.
+#[derive(Debug)]
+pub struct Guard {
+    // A guard is tied to the thread that created it.
+    marker: PhantomData<*mut ()>,
+}
+
+/// Pin the current thread, the returned guard keeps the thread pinned until it goes away.
+///
+/// # Panics
+///
+/// Panics if called while the thread-local state of the current thread is being destroyed.
+pub fn pin() -> Guard {
+    LOCAL.with(Local::pin);
+    Guard {
+        marker: PhantomData,
+    }
+}
+
+impl Guard {
+    /// Drop the boxed value at `ptr` once no pinned thread can be accessing it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from [`Box::into_raw`], and it must have been made unreachable for threads
+    /// that pin themselves from now on. It must be fine to drop the value on another thread, and
+    /// the value must not borrow anything that might go away before it is dropped.
+    pub unsafe fn defer_destroy<T>(&self, ptr: *mut T) {
+        unsafe fn drop_box<T>(ptr: *mut ()) {
+            // SAFETY: The pointer comes from `Box::into_raw` and is not accessed anymore.
+            drop(unsafe { Box::from_raw(ptr.cast::<T>()) });
+        }
+        LOCAL.with(|local| {
+            local.defer(Deferred {
+                ptr: ptr.cast(),
+                call: drop_box::<T>,
+            })
+        });
+    }
+
+    /// Try to advance the global epoch and run the deferred functions that are safe to run.
+    ///
+    /// Deferred functions are otherwise collected in batches, this is mostly useful for tests.
+    pub fn flush(&self) {
+        LOCAL.with(Local::collect);
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // The thread-local state outlives the guards, since they are tied to the thread.
+        LOCAL.with(Local::unpin);
+    }
+}
+
+impl Local {
+    /// Claim a released participant record, or create a new one.
+    fn register() -> Self {
+        let mut p = PARTICIPANTS.load(Ordering::Acquire);
+        while !p.is_null() {
+            // SAFETY: Participant records are never freed.
+            let participant = unsafe { &*p };
+            if participant
+                .in_use
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return Self::new(participant);
+            }
+            p = participant.next;
+        }
+
+        let participant = Box::into_raw(Box::new(Participant {
+            state: AtomicUsize::new(0),
+            in_use: AtomicBool::new(true),
+            next: PARTICIPANTS.load(Ordering::Relaxed),
+        }));
+        // SAFETY: The record has just been allocated, and no other thread can access it yet.
+        let next = unsafe { &mut (*participant).next };
+        while let Err(head) = PARTICIPANTS.compare_exchange_weak(
+            *next,
+            participant,
+            Ordering::Release,
+            Ordering::Relaxed,
+        ) {
+            *next = head;
+        }
+        // SAFETY: The record is leaked, so it lives for the rest of the program.
+        Self::new(unsafe { &*participant })
+    }
+
+    fn new(participant: &'static Participant) -> Self {
+        Self {
+            participant,
+            guards: Cell::new(0),
+            bag: RefCell::new(Vec::new()),
+            bag_epoch: Cell::new(0),
+            sealed: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn pin(&self) {
+        let guards = self.guards.get();
+        self.guards.set(guards + 1);
+        if guards == 0 {
+            let epoch = EPOCH.load(Ordering::Relaxed);
+            self.participant
+                .state
+                .store((epoch << 1) | 1, Ordering::Relaxed);
+            // Make the pinning visible to every thread before we load any pointer from a
+            // lock-free structure. This pairs with the fence in `try_advance`.
+            atomic::fence(Ordering::SeqCst);
+        }
+    }
+
+    fn unpin(&self) {
+        let guards = self.guards.get();
+        self.guards.set(guards - 1);
+        if guards == 1 {
+            // Every access done while pinned happens before a thread sees that we are unpinned.
+            self.participant.state.store(0, Ordering::Release);
+        }
+    }
+
+    fn defer(&self, deferred: Deferred) {
+        // Epochs only move forward, so this is the latest epoch among the deferred functions.
+        self.bag_epoch
+            .set(self.participant.state.load(Ordering::Relaxed) >> 1);
+        let mut bag = self.bag.borrow_mut();
+        bag.push(deferred);
+        let full = bag.len() >= COLLECT_THRESHOLD;
+        drop(bag);
+        if full {
+            self.collect();
+        }
+    }
+
+    /// Move the functions deferred since the last collection to a new sealed bag.
+    fn seal(&self) {
+        let deferred = std::mem::take(&mut *self.bag.borrow_mut());
+        if !deferred.is_empty() {
+            self.sealed.borrow_mut().push_back(Bag {
+                epoch: self.bag_epoch.get(),
+                deferred,
+            });
+        }
+    }
+
+    /// Try to advance the epoch, then run the deferred functions of this thread and of the
+    /// exited threads that are old enough.
+    fn collect(&self) {
+        self.seal();
+        let epoch = try_advance();
+        let mut ready = Vec::new();
+        let mut sealed = self.sealed.borrow_mut();
+        while sealed.front().is_some_and(|bag| bag.is_ready(epoch)) {
+            ready.extend(sealed.pop_front());
+        }
+        // The bags are not borrowed while running the deferred functions, since they might
+        // defer more of them.
+        drop(sealed);
+
+        // Adopt the bags left behind by exited threads.
+        let mut orphan = ORPHANS.swap(ptr::null_mut(), Ordering::Acquire);
+        while !orphan.is_null() {
+            // SAFETY: The list was detached from `ORPHANS`, so we own all of its entries.
+            let Orphan { bag, next } = *unsafe { Box::from_raw(orphan) };
+            if bag.is_ready(epoch) {
+                ready.push(bag);
+            } else {
+                push_orphan(bag);
+            }
+            orphan = next;
+        }
+
+        for bag in ready {
+            bag.run();
+        }
+    }
+}
+
+impl Drop for Local {
+    fn drop(&mut self) {
+        self.seal();
+        for bag in std::mem::take(&mut *self.sealed.borrow_mut()) {
+            push_orphan(bag);
+        }
+        self.participant.state.store(0, Ordering::Release);
+        self.participant.in_use.store(false, Ordering::Release);
+    }
+}
+
+/// Leave a bag for another thread to run.
+fn push_orphan(bag: Bag) {
+    let orphan = Box::into_raw(Box::new(Orphan {
+        bag,
+        next: ORPHANS.load(Ordering::Relaxed),
+    }));
+    // SAFETY: The entry has just been allocated, and no other thread can access it yet.
+    let next = unsafe { &mut (*orphan).next };
+    while let Err(head) =
+        ORPHANS.compare_exchange_weak(*next, orphan, Ordering::Release, Ordering::Relaxed)
+    {
+        *next = head;
+    }
+}
+
+/// Advance the global epoch if every pinned thread has observed its current value, returns the
+/// global epoch.
+fn try_advance() -> usize {
+    let epoch = EPOCH.load(Ordering::Relaxed);
+    // Pairs with the fence in `Local::pin`: either we see that a thread is pinned, or the thread
+    // sees every pointer that was unlinked before the epoch was advanced.
+    atomic::fence(Ordering::SeqCst);
+
+    let mut p = PARTICIPANTS.load(Ordering::Acquire);
+    while !p.is_null() {
+        // SAFETY: Participant records are never freed.
+        let participant = unsafe { &*p };
+        let state = participant.state.load(Ordering::Relaxed);
+        if state & 1 == 1 && state >> 1 != epoch {
+            return epoch;
+        }
+        p = participant.next;
+    }
+    // Every access done by the threads that were pinned at the previous epoch happens before
+    // the deferred functions run.
+    atomic::fence(Ordering::Acquire);
+
+    match EPOCH.compare_exchange(
+        epoch,
+        epoch.wrapping_add(1),
+        Ordering::Release,
+        Ordering::Relaxed,
+    ) {
+        Ok(_) => epoch.wrapping_add(1),
+        Err(current) => current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::Arc;
+    use std::thread;
+
+    /// Increment the shared counter when dropped.
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn deferred_values_are_dropped_once_unpinned() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let guard = pin();
+        let ptr = Box::into_raw(Box::new(DropCounter(drops.clone())));
+        // SAFETY: The value was never shared.
+        unsafe { guard.defer_destroy(ptr) };
+
+        // The value can not be dropped while the thread that retired it is pinned.
+        guard.flush();
+        guard.flush();
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        drop(guard);
+
+        // Other tests might pin threads concurrently, so keep trying.
+        while drops.load(Ordering::Relaxed) == 0 {
+            pin().flush();
+            thread::yield_now();
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn values_left_by_exited_threads_are_dropped() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        {
+            let drops = drops.clone();
+            thread::spawn(move || {
+                let guard = pin();
+                let ptr = Box::into_raw(Box::new(DropCounter(drops)));
+                // SAFETY: The value was never shared.
+                unsafe { guard.defer_destroy(ptr) };
+            })
+            .join()
+            .unwrap();
+        }
+        while drops.load(Ordering::Relaxed) == 0 {
+            pin().flush();
+            thread::yield_now();
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+}
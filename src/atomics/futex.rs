@@ -0,0 +1,242 @@
+//! A futex-like interface for blocking threads, built on [`std::thread::park`] and
+//! [`std::thread::Thread::unpark`].
+//!
+//! Threads wait on the address of an atomic value. Waiters are kept in one of a fixed number of
+//! queues picked by hashing that address. Each queue is protected by a small spin lock, which is
+//! held while the value is checked in [`wait`] and while waiters are woken in [`wake_one`] and
+//! [`wake_all`]. A thread that changes the value before waking the waiters can therefore never
+//! miss a thread that is about to wait for the old value.
+
+use super::Arc;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// The number of queues that waiters are spread across.
+const N_BUCKETS: usize = 64;
+
+static BUCKETS: [Bucket; N_BUCKETS] = [const { Bucket::new() }; N_BUCKETS];
+
+/// A thread blocked in [`wait`].
+#[derive(Debug)]
+struct Waiter {
+    /// The address of the atomic value the thread is waiting on.
+    addr: usize,
+    thread: Thread,
+    /// Set by the thread that wakes the waiter, this allows the waiter to tell a wake up apart
+    /// from a spurious return of [`thread::park`].
+    notified: AtomicBool,
+}
+
+/// A queue of waiters protected by a spin lock.
+#[derive(Debug)]
+struct Bucket {
+    locked: AtomicBool,
+    waiters: UnsafeCell<VecDeque<Arc<Waiter>>>,
+}
+
+// SAFETY: The queue of waiters is only accessed while holding the spin lock.
+unsafe impl Sync for Bucket {}
+
+impl Bucket {
+    const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            waiters: UnsafeCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Run `f` on the queue of waiters while holding the spin lock. The lock is only ever held
+    /// for a handful of instructions, so spinning is fine here.
+    fn with_waiters<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut VecDeque<Arc<Waiter>>) -> R,
+    {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                std::hint::spin_loop();
+            }
+        }
+        // SAFETY: We are holding the lock.
+        let rtr = f(unsafe { &mut *self.waiters.get() });
+        self.locked.store(false, Ordering::Release);
+        rtr
+    }
+}
+
+/// Get the queue of waiters for the atomic value.
+fn bucket(atomic: &AtomicU32) -> (usize, &'static Bucket) {
+    let addr = atomic as *const AtomicU32 as usize;
+    // Atomic values are aligned, so the lowest bits are always the same.
+    (addr, &BUCKETS[(addr >> 2) % N_BUCKETS])
+}
+
+/// Block the current thread as long as `atomic` holds `expected`, until it is woken by
+/// [`wake_one`] or [`wake_all`].
+///
+/// Returns immediately if `atomic` does not hold `expected`. Callers must re-check their
+/// condition after returning.
+pub(crate) fn wait(atomic: &AtomicU32, expected: u32) {
+    wait_until(atomic, expected, None);
+}
+
+/// Same as [`wait`], but gives up after `timeout` has elapsed. Returns `false` if the timeout
+/// elapsed without the thread being woken.
+pub(crate) fn wait_timeout(atomic: &AtomicU32, expected: u32, timeout: Duration) -> bool {
+    wait_until(atomic, expected, Instant::now().checked_add(timeout))
+}
+
+fn wait_until(atomic: &AtomicU32, expected: u32, deadline: Option<Instant>) -> bool {
+    let (addr, bucket) = bucket(atomic);
+    let waiter = bucket.with_waiters(|waiters| {
+        // The check and the insertion happen while holding the lock of the bucket, so a thread
+        // that changes the value and then calls `wake_*` either sees us in the queue or makes us
+        // see the new value here.
+        if atomic.load(Ordering::Acquire) != expected {
+            return None;
+        }
+        let waiter = Arc::new(Waiter {
+            addr,
+            thread: thread::current(),
+            notified: AtomicBool::new(false),
+        });
+        waiters.push_back(waiter.clone());
+        Some(waiter)
+    });
+    let waiter = match waiter {
+        Some(waiter) => waiter,
+        None => return true,
+    };
+
+    while !waiter.notified.load(Ordering::Acquire) {
+        match deadline {
+            None => thread::park(),
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    // Withdraw from the queue, unless we were woken concurrently, in which case
+                    // the waker has already set the flag while holding the lock.
+                    return bucket.with_waiters(|waiters| {
+                        match waiters.iter().position(|w| Arc::ptr_eq(w, &waiter)) {
+                            Some(idx) => {
+                                waiters.remove(idx);
+                                false
+                            }
+                            None => true,
+                        }
+                    });
+                }
+                thread::park_timeout(deadline - now);
+            }
+        }
+    }
+    true
+}
+
+/// Wake up one thread waiting on `atomic`, returns whether there was one.
+pub(crate) fn wake_one(atomic: &AtomicU32) -> bool {
+    wake(atomic, 1) == 1
+}
+
+/// Wake up all threads waiting on `atomic`, returns how many there were.
+pub(crate) fn wake_all(atomic: &AtomicU32) -> usize {
+    wake(atomic, usize::MAX)
+}
+
+fn wake(atomic: &AtomicU32, n: usize) -> usize {
+    let (addr, bucket) = bucket(atomic);
+    let mut woken = Vec::new();
+    bucket.with_waiters(|waiters| {
+        // Waiters on other addresses sharing the bucket are kept in order.
+        let mut idx = 0;
+        while idx < waiters.len() && woken.len() < n {
+            if waiters[idx].addr == addr {
+                let waiter = waiters.remove(idx).expect("Index is in bounds");
+                waiter.notified.store(true, Ordering::Release);
+                woken.push(waiter);
+            } else {
+                idx += 1;
+            }
+        }
+    });
+    // Unpark outside of the lock so the woken threads do not immediately contend on it.
+    for waiter in woken.iter() {
+        waiter.thread.unpark();
+    }
+    woken.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_returns_when_value_differs() {
+        let atomic = AtomicU32::new(1);
+        wait(&atomic, 0);
+        assert!(!wake_one(&atomic));
+    }
+
+    #[test]
+    fn wait_timeout_elapses() {
+        let atomic = AtomicU32::new(0);
+        assert!(!wait_timeout(&atomic, 0, Duration::from_millis(10)));
+        assert!(!wake_one(&atomic));
+    }
+
+    #[test]
+    fn wake_one_wakes_waiters_in_turn() {
+        const N_THREADS: usize = 8;
+
+        let atomic = Arc::new(AtomicU32::new(0));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                let atomic = atomic.clone();
+                thread::spawn(move || {
+                    while atomic.load(Ordering::Acquire) == 0 {
+                        wait(&atomic, 0);
+                    }
+                })
+            })
+            .collect();
+
+        atomic.store(1, Ordering::Release);
+        for _ in 0..N_THREADS {
+            wake_one(&atomic);
+        }
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn wake_all_wakes_every_waiter() {
+        const N_THREADS: usize = 8;
+
+        let atomic = Arc::new(AtomicU32::new(0));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                let atomic = atomic.clone();
+                thread::spawn(move || {
+                    while atomic.load(Ordering::Acquire) == 0 {
+                        wait(&atomic, 0);
+                    }
+                })
+            })
+            .collect();
+
+        // Give the threads a chance to park before waking them.
+        thread::sleep(Duration::from_millis(20));
+        atomic.store(1, Ordering::Release);
+        wake_all(&atomic);
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use super::OnceLock;
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ops::Deref;
+
+/// A value that is initialized on its first access, and that can be shared between threads.
+///
+/// The initializer runs at most once, threads accessing the value while it is running block until
+/// it is done. If the initializer panics, the value is poisoned and every following access panics.
+pub struct LazyLock<T, F = fn() -> T> {
+    once: OnceLock<T>,
+    /// Taken by the thread running the initializer.
+    init: UnsafeCell<Option<F>>,
+}
+
+// SAFETY: The initializer is only accessed by the single thread running it, which might not be
+// the thread that created the `LazyLock`. The value is read from all threads.
+unsafe impl<T, F> Sync for LazyLock<T, F>
+where
+    T: Send + Sync,
+    F: Send,
+{
+}
+
+impl<T, F: FnOnce() -> T> LazyLock<T, F> {
+    /// Create a value that is initialized by `f` on its first access.
+    pub const fn new(f: F) -> Self {
+        Self {
+            once: OnceLock::new(),
+            init: UnsafeCell::new(Some(f)),
+        }
+    }
+
+    /// Force the initialization and get a reference to the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initializer panics, or if it has panicked before.
+    pub fn force(this: &LazyLock<T, F>) -> &T {
+        this.once.get_or_init(|| {
+            // SAFETY: The `OnceLock` lets a single thread at a time run this closure.
+            match unsafe { (*this.init.get()).take() } {
+                Some(f) => f(),
+                None => panic!("LazyLock instance has previously been poisoned"),
+            }
+        })
+    }
+
+    /// Consume the value and return it, or the initializer if it has not run yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value has been poisoned.
+    pub fn into_inner(this: Self) -> Result<T, F> {
+        let LazyLock { once, init } = this;
+        match (once.into_inner(), init.into_inner()) {
+            (Some(value), _) => Ok(value),
+            (None, Some(f)) => Err(f),
+            (None, None) => panic!("LazyLock instance has previously been poisoned"),
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for LazyLock<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        LazyLock::force(self)
+    }
+}
+
+impl<T: Default> Default for LazyLock<T> {
+    fn default() -> Self {
+        LazyLock::new(T::default)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for LazyLock<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.once.get() {
+            Some(value) => f.debug_tuple("LazyLock").field(value).finish(),
+            None => f.write_str("LazyLock(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    static GLOBAL: LazyLock<Vec<usize>> = LazyLock::new(|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        (0..10).collect()
+    });
+
+    #[test]
+    fn static_is_initialized_once() {
+        let handles: Vec<_> = (0..8)
+            .map(|_| thread::spawn(|| GLOBAL.iter().sum::<usize>()))
+            .collect();
+        for h in handles {
+            assert_eq!(h.join().unwrap(), 45);
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn into_inner_gives_back_the_initializer() {
+        let lazy = LazyLock::new(|| 42);
+        let f = LazyLock::into_inner(lazy).unwrap_err();
+        let lazy = LazyLock::new(f);
+        assert_eq!(*lazy, 42);
+        assert_eq!(LazyLock::into_inner(lazy).ok(), Some(42));
+    }
+
+    #[test]
+    fn panicking_initializer_poisons_the_value() {
+        let lazy = LazyLock::new(|| -> i32 { panic!("init failed") });
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| *lazy)).is_err());
+        let msg = panic::catch_unwind(AssertUnwindSafe(|| *lazy)).unwrap_err();
+        assert_eq!(
+            msg.downcast_ref::<&str>(),
+            Some(&"LazyLock instance has previously been poisoned")
+        );
+    }
+}
@@ -0,0 +1,658 @@
+//! Multi-producer, single-consumer channels for sending values between threads.
+//!
+//! A [`channel`] has an unbounded buffer, so sending never blocks. A [`sync_channel`] has a
+//! buffer of a fixed size, sending blocks while it is full. With a size of zero, every send blocks
+//! until the value is received.
+//!
+//! The channel is disconnected when all the senders or the receiver go away, which is reported by
+//! the sending and receiving methods.
+
+use super::{Arc, Condvar, Mutex, MutexGuard};
+use crate::collections::DoublyLinkedList;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// The state that is shared between the two halves of a channel.
+#[derive(Debug)]
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    /// Notified when a value is sent or when the last sender goes away.
+    available: Condvar,
+    /// Notified when a value is received or when the receiver goes away.
+    space: Condvar,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    queue: DoublyLinkedList<T>,
+    /// The maximum number of values in the queue, `None` if the channel is unbounded.
+    bound: Option<usize>,
+    senders: usize,
+    receiver_alive: bool,
+    /// Whether the receiver is blocked waiting for a value.
+    receiver_waiting: bool,
+    /// Whether the value in a rendezvous channel belongs to a sender that is blocked until it is
+    /// received, and that takes it back if the receiver goes away.
+    sender_waiting: bool,
+    /// The number of values received so far, which lets the sender of a rendezvous channel know
+    /// when its value has been taken.
+    received: usize,
+}
+
+impl<T> Shared<T> {
+    fn new(bound: Option<usize>) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                queue: DoublyLinkedList::new(),
+                bound,
+                senders: 1,
+                receiver_alive: true,
+                receiver_waiting: false,
+                sender_waiting: false,
+                received: 0,
+            }),
+            available: Condvar::new(),
+            space: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock()
+    }
+
+    fn add_sender(&self) {
+        self.lock().senders += 1;
+    }
+
+    fn remove_sender(&self) {
+        let mut inner = self.lock();
+        inner.senders -= 1;
+        if inner.senders == 0 {
+            drop(inner);
+            self.available.notify_all();
+        }
+    }
+
+    fn send(&self, t: T) -> Result<(), SendError<T>> {
+        let mut inner = self
+            .space
+            .wait_while(self.lock(), |inner| inner.receiver_alive && inner.is_full());
+        if !inner.receiver_alive {
+            return Err(SendError(t));
+        }
+        inner.queue.push_back(t);
+        self.available.notify_one();
+        if inner.bound == Some(0) {
+            // Other senders wait for the queue to be empty before pushing, so our value is the
+            // only one in it, and the next one to be received.
+            inner.sender_waiting = true;
+            let received = inner.received;
+            inner = self.space.wait_while(inner, |inner| {
+                inner.received == received && inner.receiver_alive
+            });
+            if inner.received == received {
+                // The receiver went away without taking the value.
+                let t = inner.queue.pop_front().expect("The value was not received");
+                return Err(SendError(t));
+            }
+        }
+        Ok(())
+    }
+
+    fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        let mut inner = self.lock();
+        if !inner.receiver_alive {
+            return Err(TrySendError::Disconnected(t));
+        }
+        // A rendezvous channel only accepts a value if the receiver is already waiting for it.
+        if inner.is_full() || (inner.bound == Some(0) && !inner.receiver_waiting) {
+            return Err(TrySendError::Full(t));
+        }
+        inner.queue.push_back(t);
+        inner.sender_waiting = false;
+        drop(inner);
+        self.available.notify_one();
+        Ok(())
+    }
+
+    /// Pop a value from the queue and let the senders waiting on the buffer know about it.
+    fn pop(&self, mut inner: MutexGuard<'_, Inner<T>>) -> Option<T> {
+        let t = inner.queue.pop_front()?;
+        inner.received = inner.received.wrapping_add(1);
+        let rendezvous = inner.bound == Some(0);
+        drop(inner);
+        if rendezvous {
+            // Both the sender of the value and the senders waiting for the buffer to be empty
+            // must be woken up.
+            self.space.notify_all();
+        } else {
+            self.space.notify_one();
+        }
+        Some(t)
+    }
+
+    /// Pop a value from the queue, waiting until `deadline` if there is none.
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let mut inner = self.lock();
+        loop {
+            if !inner.queue.is_empty() {
+                return Ok(self.pop(inner).expect("The queue is not empty"));
+            }
+            if inner.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            inner.receiver_waiting = true;
+            inner = match deadline {
+                None => self.available.wait(inner),
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        inner.receiver_waiting = false;
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.available.wait_timeout(inner, timeout).0
+                }
+            };
+            inner.receiver_waiting = false;
+        }
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let inner = self.lock();
+        if !inner.queue.is_empty() {
+            Ok(self.pop(inner).expect("The queue is not empty"))
+        } else if inner.senders == 0 {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    fn is_full(&self) -> bool {
+        match self.bound {
+            // A rendezvous channel holds at most the value whose sender waits for it to be
+            // received.
+            Some(0) => !self.queue.is_empty(),
+            Some(bound) => self.queue.len() >= bound,
+            None => false,
+        }
+    }
+}
+
+/// Create an unbounded channel, returns its sending half and its receiving half.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared::new(None));
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver::new(shared),
+    )
+}
+
+/// Create a channel whose buffer holds at most `bound` values, returns its sending half and its
+/// receiving half.
+///
+/// With a `bound` of zero, every send blocks until the receiver takes the value.
+pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared::new(Some(bound)));
+    (
+        SyncSender {
+            shared: shared.clone(),
+        },
+        Receiver::new(shared),
+    )
+}
+
+/// The sending half of a [`channel`], which can be cloned to send from multiple threads.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Send a value to the receiver, this never blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value back if the receiver has gone away.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.shared.send(t)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.add_sender();
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.remove_sender();
+    }
+}
+
+/// The sending half of a [`sync_channel`], which can be cloned to send from multiple threads.
+#[derive(Debug)]
+pub struct SyncSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> SyncSender<T> {
+    /// Send a value to the receiver, blocking while the buffer is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value back if the receiver has gone away.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.shared.send(t)
+    }
+
+    /// Attempt to send a value to the receiver without waiting for space in the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value back if the buffer is full or if the receiver has gone away.
+    pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        self.shared.try_send(t)
+    }
+}
+
+impl<T> Clone for SyncSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.add_sender();
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for SyncSender<T> {
+    fn drop(&mut self) {
+        self.shared.remove_sender();
+    }
+}
+
+/// The receiving half of a [`channel`] or a [`sync_channel`].
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    // There is a single consumer, so the receiver can be moved to another thread but not shared.
+    marker: PhantomData<std::cell::Cell<()>>,
+}
+
+impl<T> Receiver<T> {
+    fn new(shared: Arc<Shared<T>>) -> Self {
+        Self {
+            shared,
+            marker: PhantomData,
+        }
+    }
+
+    /// Receive a value, blocking until one is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RecvError`] if the buffer is empty and all the senders have gone away.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.shared.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Attempt to receive a value without blocking.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TryRecvError::Empty`] if there is no value, or [`TryRecvError::Disconnected`] if
+    /// the buffer is empty and all the senders have gone away.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.shared.try_recv()
+    }
+
+    /// Receive a value, blocking until one is available or until `timeout` has elapsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RecvTimeoutError::Timeout`] if no value was sent in time, or
+    /// [`RecvTimeoutError::Disconnected`] if the buffer is empty and all the senders have gone
+    /// away.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.shared.recv_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Return an iterator that blocks waiting for values, and ends once all the senders have gone
+    /// away.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Return an iterator over the values that are available without blocking.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.receiver_alive = false;
+        // The value in a rendezvous channel is given back to its sender, if it is waiting for it.
+        let queue = if inner.sender_waiting {
+            DoublyLinkedList::new()
+        } else {
+            std::mem::take(&mut inner.queue)
+        };
+        drop(inner);
+        self.shared.space.notify_all();
+        // The values that were never received are dropped without holding the lock.
+        drop(queue);
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { rx: self }
+    }
+}
+
+/// A blocking iterator over the values received by a [`Receiver`].
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+/// A non-blocking iterator over the values received by a [`Receiver`].
+#[derive(Debug)]
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// A blocking iterator that owns a [`Receiver`].
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+/// An error returned when sending to a channel whose receiver has gone away. It carries the value
+/// that could not be sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+/// An error returned by [`SyncSender::try_send`]. It carries the value that could not be sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// The buffer of the channel is full.
+    Full(T),
+    /// The receiver has gone away.
+    Disconnected(T),
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("sending on a full channel"),
+            Self::Disconnected(_) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(SendError(t): SendError<T>) -> Self {
+        Self::Disconnected(t)
+    }
+}
+
+/// An error returned by [`Receiver::recv`] when all the senders have gone away.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("receiving on a closed channel")
+    }
+}
+
+impl Error for RecvError {}
+
+/// An error returned by [`Receiver::try_recv`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TryRecvError {
+    /// There is no value available right now.
+    Empty,
+    /// All the senders have gone away and there is no value left.
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("receiving on an empty channel"),
+            Self::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+/// An error returned by [`Receiver::recv_timeout`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecvTimeoutError {
+    /// No value was sent before the timeout elapsed.
+    Timeout,
+    /// All the senders have gone away and there is no value left.
+    Disconnected,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("timed out waiting on a channel"),
+            Self::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn values_arrive_in_order_from_each_sender() {
+        const N_THREADS: usize = 4;
+        const N_ITEMS: usize = 1000;
+
+        let (tx, rx) = channel();
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|t| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..N_ITEMS {
+                        tx.send((t, i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        // The iterator ends once every sender has gone away.
+        let mut next = [0; N_THREADS];
+        for (t, i) in rx {
+            assert_eq!(next[t], i);
+            next[t] += 1;
+        }
+        assert!(next.iter().all(|&n| n == N_ITEMS));
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn disconnection_is_reported() {
+        let (tx, rx) = channel();
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        tx.send(2).unwrap();
+        drop(tx);
+        // Values that were sent are still received after the senders have gone away.
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, rx) = channel();
+        drop(rx);
+        assert_eq!(tx.send(3), Err(SendError(3)));
+    }
+
+    #[test]
+    fn bounded_channel_blocks_when_full() {
+        let (tx, rx) = sync_channel(2);
+        tx.send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+
+        let handle = thread::spawn(move || {
+            tx.send(3).unwrap();
+            tx.send(4).unwrap();
+        });
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn rendezvous_channel_waits_for_receiver() {
+        let (tx, rx) = sync_channel(0);
+        assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+
+        let handle = thread::spawn(move || {
+            for i in 0..100 {
+                tx.send(i).unwrap();
+            }
+            tx
+        });
+        assert_eq!(rx.iter().take(100).sum::<i32>(), 4950);
+        let tx = handle.join().unwrap();
+
+        // The value is given back if the receiver goes away before taking it.
+        let handle = thread::spawn(move || tx.send(100));
+        thread::sleep(Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(handle.join().unwrap(), Err(SendError(100)));
+    }
+
+    #[test]
+    fn rendezvous_value_without_waiting_sender_is_dropped_with_the_receiver() {
+        let value = Arc::new(());
+        let (tx, rx) = sync_channel(0);
+        // Pretend that the receiver was waiting, so that `try_send` leaves its value in the
+        // channel, and went away before taking it.
+        rx.shared.lock().receiver_waiting = true;
+        tx.try_send(value.clone()).unwrap();
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+        assert!(matches!(
+            tx.try_send(value),
+            Err(TrySendError::Disconnected(_))
+        ));
+    }
+
+    #[test]
+    fn unreceived_values_are_dropped_with_the_receiver() {
+        let value = Arc::new(());
+        let (tx, rx) = channel();
+        tx.send(value.clone()).unwrap();
+        tx.send(value.clone()).unwrap();
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
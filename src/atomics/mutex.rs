@@ -1,13 +1,28 @@
+use super::futex;
+use super::poison::{self, LockResult, PoisonError, TryLockError, TryLockResult};
 use std::cell::UnsafeCell;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU32, Ordering};
 
-const LOCKED: bool = true;
-const UNLOCKED: bool = false;
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// The lock is held and some threads might be parked waiting for it.
+const CONTENDED: u32 = 2;
+
+/// The number of times a thread spins on a held lock before parking.
+const SPIN_LIMIT: usize = 100;
 
 /// A structure for providing mutually exclusive access to shared data.
+///
+/// A thread that finds the lock held spins for a short while, then parks until the thread holding
+/// the lock wakes it up.
+///
+/// If a thread panics while holding the lock, the lock is released while unwinding, and the next
+/// thread acquires it as usual. Use a [`PoisoningMutex`] to find out about such panics instead.
 #[derive(Debug)]
 pub struct Mutex<T> {
-    locked: AtomicBool,
+    state: AtomicU32,
     v: UnsafeCell<T>,
 }
 
@@ -18,23 +33,63 @@ impl<T> Mutex<T> {
     /// Wrap the given value in a `Mutex` to provide safe concurrent accesses from multiple threads.
     pub fn new(t: T) -> Self {
         Self {
-            locked: AtomicBool::new(UNLOCKED),
+            state: AtomicU32::new(UNLOCKED),
             v: UnsafeCell::new(t),
         }
     }
 
-    /// Acquire exlusive access and perform an action on a mutable reference to the inner value.
-    pub fn with_lock<F, R>(&self, f: F) -> R
-    where
-        F: FnOnce(&mut T) -> R,
-    {
-        while self
-            .locked
-            .compare_exchange_weak(UNLOCKED, LOCKED, Ordering::AcqRel, Ordering::Relaxed)
+    /// Acquire exclusive access to the inner value, blocking the current thread until the lock is
+    /// available.
+    ///
+    /// The lock is released when the returned guard is dropped.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        // The uncontended case only takes a single compare-and-swap.
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
             .is_err()
         {
-            while self.locked.load(Ordering::Relaxed) == LOCKED {
-                std::hint::spin_loop()
+            self.lock_contended();
+        }
+
+        // With ordering [`Relaxed`], there is no gurantee that the value you receive is in order
+        // with the operations performed by other thread.
+        //
+        // The [`Acquire`] and [`Release`] pair of memory ordering ensures that any operation
+        // before one thread releases a memory location is observed by the thread that subsequently
+        // acquires the same memory location
+        MutexGuard {
+            mutex: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Attempt to acquire exclusive access to the inner value without waiting, returns `None` if
+    /// the lock is already held.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard {
+                mutex: self,
+                marker: PhantomData,
+            })
+    }
+
+    #[cold]
+    fn lock_contended(&self) {
+        // Spin for a short while in case the lock is only held briefly. There is no point in
+        // spinning if other threads are already parked.
+        for _ in 0..SPIN_LIMIT {
+            match self.state.compare_exchange_weak(
+                UNLOCKED,
+                LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(CONTENDED) => break,
+                Err(_) => std::hint::spin_loop(),
             }
 
             // compare_exchange_weak might fail even if the value matches that one that we give
@@ -50,23 +105,244 @@ impl<T> Mutex<T> {
             //  - compare_exchange_weak: LDREX STREX
         }
 
-        // With ordering [`Relaxed`], there is no gurantee that the value you receive is in order
-        // with the operations performed by other thread.
-        //
-        // The [`Acquire`] and [`Release`] pair of memory ordering ensures that any operation
-        // before one thread releases a memory location is observed by the thread that subsequently
-        // acquires the same memory location
+        // Mark the lock as contended so that the thread holding it wakes us up when unlocking. If
+        // the lock was released in the meantime, we now hold it. It stays marked as contended
+        // since we cannot know whether other threads are still parked.
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            futex::wait(&self.state, CONTENDED);
+        }
+    }
+
+    /// Release the lock, waking up one of the parked threads if there might be any.
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex::wake_one(&self.state);
+        }
+    }
+
+    /// Acquire exlusive access and perform an action on a mutable reference to the inner value.
+    ///
+    /// The lock is released even if `f` panics.
+    pub fn with_lock<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        f(&mut self.lock())
+    }
 
+    /// Get a mutable reference to the inner value. No locking is needed since the mutable borrow
+    /// guarantees that there is no other access to the `Mutex`.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.v.get_mut()
+    }
+
+    /// Consume the `Mutex` and return the inner value.
+    pub fn into_inner(self) -> T {
+        self.v.into_inner()
+    }
+}
+
+/// A guard giving exclusive access to the value of a locked [`Mutex`]. The lock is released when
+/// the guard is dropped.
+///
+/// This struct is created by [`Mutex::lock()`] and [`Mutex::try_lock()`].
+#[derive(Debug)]
+pub struct MutexGuard<'a, T> {
+    /// Used by [`Condvar`](super::Condvar) to reacquire the lock after waiting.
+    pub(super) mutex: &'a Mutex<T>,
+    // The guard acts like a mutable reference to the value, this makes it [`Sync`] only if the
+    // value is [`Sync`].
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
         // SAFETY: We are holding a lock
-        let rtr = f(unsafe { &mut *self.v.get() });
-        self.locked.store(UNLOCKED, Ordering::Release);
-        rtr
+        unsafe { &*self.mutex.v.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: We are holding a lock
+        unsafe { &mut *self.mutex.v.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+/// A [`Mutex`] that gets poisoned when a thread panics while holding its lock.
+///
+/// Once poisoned, every following attempt to acquire the lock returns a [`PoisonError`], since the
+/// panicking thread might have left the inner value in an inconsistent state. The lock itself is
+/// still released, so other threads can choose to ignore the poisoning, or clear it with
+/// [`PoisoningMutex::clear_poison`] once they have restored the inner value.
+///
+/// # Examples
+///
+/// ```
+/// use rusty_crust::atomics::PoisoningMutex;
+/// use std::panic::{self, AssertUnwindSafe};
+///
+/// let m = PoisoningMutex::new(0);
+/// let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+///     m.with_lock(|v| {
+///         *v += 1;
+///         panic!("oops");
+///     })
+/// }));
+///
+/// assert!(m.is_poisoned());
+/// let v = m.lock().unwrap_or_else(|err| err.into_inner());
+/// assert_eq!(*v, 1);
+/// ```
+#[derive(Debug)]
+pub struct PoisoningMutex<T> {
+    mutex: Mutex<T>,
+    poison: poison::Flag,
+}
+
+impl<T> PoisoningMutex<T> {
+    /// Wrap the given value in a `PoisoningMutex` to provide safe concurrent accesses from
+    /// multiple threads.
+    pub fn new(t: T) -> Self {
+        Self {
+            mutex: Mutex::new(t),
+            poison: poison::Flag::new(),
+        }
+    }
+
+    /// Acquire exclusive access to the inner value, blocking the current thread until the lock is
+    /// available.
+    ///
+    /// The lock is released when the returned guard is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the guard if the mutex is poisoned.
+    pub fn lock(&self) -> LockResult<PoisoningMutexGuard<'_, T>> {
+        self.guard(self.mutex.lock())
+    }
+
+    /// Attempt to acquire exclusive access to the inner value without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TryLockError::WouldBlock`] if the lock is already held, and
+    /// [`TryLockError::Poisoned`] holding the guard if the mutex is poisoned.
+    pub fn try_lock(&self) -> TryLockResult<PoisoningMutexGuard<'_, T>> {
+        let guard = self.mutex.try_lock().ok_or(TryLockError::WouldBlock)?;
+        Ok(self.guard(guard)?)
+    }
+
+    /// Returns true if a thread panicked while holding the lock.
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+
+    /// Clear the poisoned state of the mutex, so that it can be acquired without errors again.
+    pub fn clear_poison(&self) {
+        self.poison.clear();
+    }
+
+    /// Acquire exlusive access and perform an action on a mutable reference to the inner value.
+    ///
+    /// The lock is released even if `f` panics, in which case the mutex becomes poisoned.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the result of `f` if the mutex is poisoned. The action is
+    /// performed either way.
+    pub fn with_lock<F, R>(&self, f: F) -> LockResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        poison::map_result(self.lock(), |mut guard| f(&mut guard))
+    }
+
+    /// Get a mutable reference to the inner value. No locking is needed since the mutable borrow
+    /// guarantees that there is no other access to the `PoisoningMutex`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the reference if the mutex is poisoned.
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let poisoned = self.poison.get();
+        let v = self.mutex.get_mut();
+        if poisoned {
+            Err(PoisonError::new(v))
+        } else {
+            Ok(v)
+        }
+    }
+
+    /// Consume the `PoisoningMutex` and return the inner value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the value if the mutex is poisoned.
+    pub fn into_inner(self) -> LockResult<T> {
+        let poisoned = self.poison.get();
+        let v = self.mutex.into_inner();
+        if poisoned {
+            Err(PoisonError::new(v))
+        } else {
+            Ok(v)
+        }
+    }
+
+    /// Wrap the guard of the lock that the current thread has just acquired.
+    fn guard<'a>(&'a self, guard: MutexGuard<'a, T>) -> LockResult<PoisoningMutexGuard<'a, T>> {
+        poison::map_result(self.poison.guard(), |poison| PoisoningMutexGuard {
+            guard,
+            flag: &self.poison,
+            poison,
+        })
+    }
+}
+
+/// A guard giving exclusive access to the value of a locked [`PoisoningMutex`]. The lock is
+/// released when the guard is dropped, and the mutex is poisoned if the thread is panicking.
+///
+/// This struct is created by [`PoisoningMutex::lock()`] and [`PoisoningMutex::try_lock()`].
+#[derive(Debug)]
+pub struct PoisoningMutexGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    flag: &'a poison::Flag,
+    poison: poison::Guard,
+}
+
+impl<T> Deref for PoisoningMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for PoisoningMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for PoisoningMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        // The inner guard is dropped right after, releasing the lock once the flag is updated.
+        self.flag.done(&self.poison);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::atomics::Arc;
 
     #[test]
     fn example_seqcst_vs_acqrel() {
@@ -123,9 +399,10 @@ mod tests {
         const N_THREADS: usize = 100;
         const N_ITER: usize = 100;
 
-        let l: &'static _ = Box::leak(Box::new(Mutex::new(0)));
+        let l = Arc::new(Mutex::new(0));
         let handles: Vec<_> = (0..N_THREADS)
             .map(|_| {
+                let l = Arc::clone(&l);
                 std::thread::spawn(move || {
                     for _ in 0..N_ITER {
                         l.with_lock(|v| *v += 1);
@@ -188,4 +465,131 @@ mod tests {
         let _r1 = t1.join().unwrap();
         let _r2 = t2.join().unwrap();
     }
+
+    #[test]
+    fn guard_releases_lock_on_drop() {
+        let m = Mutex::new(vec![1]);
+        {
+            let mut guard = m.lock();
+            guard.push(2);
+            assert!(m.try_lock().is_none());
+        }
+        let guard = m.try_lock().unwrap();
+        assert_eq!(*guard, [1, 2]);
+        drop(guard);
+
+        let mut m = m;
+        m.get_mut().push(3);
+        assert_eq!(m.into_inner(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn concurrent_mutex_guard_add() {
+        const N_THREADS: usize = 100;
+        const N_ITER: usize = 100;
+
+        let l = Arc::new(Mutex::new(0));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                let l = Arc::clone(&l);
+                std::thread::spawn(move || {
+                    for _ in 0..N_ITER {
+                        let mut guard = l.lock();
+                        let v = *guard;
+                        *guard = v + 1;
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(*l.lock(), N_THREADS * N_ITER);
+    }
+
+    #[test]
+    fn contended_lock_parks_and_wakes() {
+        const N_THREADS: usize = 8;
+
+        let l = Arc::new(Mutex::new(Vec::new()));
+        let guard = l.lock();
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|i| {
+                let l = Arc::clone(&l);
+                std::thread::spawn(move || l.lock().push(i))
+            })
+            .collect();
+
+        // Hold the lock long enough for the other threads to give up spinning and park.
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(l.state.load(Ordering::Relaxed), CONTENDED);
+        drop(guard);
+
+        for h in handles {
+            h.join().unwrap();
+        }
+        let mut v = l.lock().clone();
+        v.sort_unstable();
+        assert_eq!(v, (0..N_THREADS).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn panic_while_locked_releases_lock() {
+        let l = Arc::new(Mutex::new(0));
+        let handle = {
+            let l = Arc::clone(&l);
+            std::thread::spawn(move || {
+                l.with_lock(|v| {
+                    *v += 1;
+                    panic!("oops");
+                })
+            })
+        };
+        assert!(handle.join().is_err());
+        assert_eq!(l.with_lock(|v| *v), 1);
+        assert_eq!(l.state.load(Ordering::Relaxed), UNLOCKED);
+    }
+
+    #[test]
+    fn panic_while_locked_poisons_mutex() {
+        let l = Arc::new(PoisoningMutex::new(0));
+        let handle = {
+            let l = Arc::clone(&l);
+            std::thread::spawn(move || {
+                l.with_lock(|v| {
+                    *v += 1;
+                    panic!("oops");
+                })
+            })
+        };
+        assert!(handle.join().is_err());
+
+        // The lock was released while unwinding.
+        assert!(l.is_poisoned());
+        let err = l.lock().unwrap_err();
+        assert_eq!(**err.get_ref(), 1);
+        *err.into_inner() += 1;
+        assert!(matches!(l.try_lock(), Err(TryLockError::Poisoned(_))));
+        assert_eq!(l.with_lock(|v| *v).unwrap_err().into_inner(), 2);
+
+        l.clear_poison();
+        assert!(!l.is_poisoned());
+        assert_eq!(*l.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn poisoned_mutex_reports_on_get_mut_and_into_inner() {
+        let mut m = PoisoningMutex::new(1);
+        {
+            let _guard = m.lock().unwrap();
+            assert!(matches!(m.try_lock(), Err(TryLockError::WouldBlock)));
+        }
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = m.lock().unwrap();
+            panic!("oops");
+        }));
+        assert!(m.get_mut().is_err());
+        assert_eq!(m.into_inner().unwrap_err().into_inner(), 1);
+    }
 }
@@ -0,0 +1,315 @@
+use super::futex;
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const INCOMPLETE: u32 = 0;
+/// A thread is running the initializer.
+const RUNNING: u32 = 1;
+/// A thread is running the initializer and some threads might be parked waiting for it.
+const QUEUED: u32 = 2;
+const COMPLETE: u32 = 3;
+/// The initializer panicked. The next thread that needs the value runs its own initializer.
+const POISONED: u32 = 4;
+
+/// A cell that can be written to only once, and that can be shared between threads.
+///
+/// Only one thread runs its initializer at a time. Other threads trying to initialize the cell
+/// concurrently block until the value is available. If the initializer panics, the cell becomes
+/// poisoned, and the next thread that needs the value runs its own initializer.
+pub struct OnceLock<T> {
+    state: AtomicU32,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value can be moved to another thread with the `OnceLock`.
+unsafe impl<T> Send for OnceLock<T> where T: Send {}
+
+// SAFETY: The value is written to by one thread and can then be read from all threads, so it
+// must be both `Send` and `Sync`.
+unsafe impl<T> Sync for OnceLock<T> where T: Send + Sync {}
+
+impl<T> OnceLock<T> {
+    /// Create an empty cell.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(INCOMPLETE),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Get a reference to the value, returns `None` if the cell is empty or being initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_complete() {
+            // SAFETY: The value is initialized.
+            Some(unsafe { self.get_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    /// Get a mutable reference to the value, returns `None` if the cell is empty.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() == COMPLETE {
+            // SAFETY: The value is initialized, and we have exclusive access to it.
+            Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Set the value of the cell, blocking if another thread is initializing it. The given value
+    /// is returned back if the cell is not empty.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().expect("The initializer runs at most once"));
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Get a reference to the value, initializing the cell with `f` if it is empty.
+    ///
+    /// Blocks if another thread is initializing the cell. Calling this from within `f` on the same
+    /// cell deadlocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` panics, the cell is poisoned in that case.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        match self.get_or_try_init(|| Ok::<T, std::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Get a reference to the value, initializing the cell with `f` if it is empty. The cell is
+    /// left empty if `f` returns an error.
+    ///
+    /// Blocks if another thread is initializing the cell. Calling this from within `f` on the same
+    /// cell deadlocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` panics, the cell is poisoned in that case.
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if !self.is_complete() {
+            self.initialize(f)?;
+        }
+        // SAFETY: The value is initialized.
+        Ok(unsafe { self.get_unchecked() })
+    }
+
+    /// Take the value out of the cell, leaving it empty.
+    pub fn take(&mut self) -> Option<T> {
+        if *self.state.get_mut() == COMPLETE {
+            *self.state.get_mut() = INCOMPLETE;
+            // SAFETY: The value was initialized, and the cell is now marked as empty so the value
+            // is not read again.
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+
+    /// Consume the cell and return its value.
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+
+    fn is_complete(&self) -> bool {
+        // Synchronizes with the store of `COMPLETE` after writing the value.
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Get a reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// The value must be initialized.
+    unsafe fn get_unchecked(&self) -> &T {
+        // SAFETY: The caller ensures that the value is initialized, and it is never written to
+        // once it is.
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+
+    /// Run `f` to initialize the cell, unless another thread does it first.
+    #[cold]
+    fn initialize<F, E>(&self, f: F) -> Result<(), E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            match state {
+                COMPLETE => return Ok(()),
+                INCOMPLETE | POISONED => {
+                    if let Err(s) = self.state.compare_exchange_weak(
+                        state,
+                        RUNNING,
+                        Ordering::Acquire,
+                        Ordering::Acquire,
+                    ) {
+                        state = s;
+                        continue;
+                    }
+                    // If `f` panics, the guard is dropped with its state left as `POISONED`.
+                    let mut guard = CompletionGuard {
+                        state: &self.state,
+                        set_state_on_drop_to: POISONED,
+                    };
+                    return match f() {
+                        Ok(value) => {
+                            // SAFETY: We are the only thread running the initializer, and no
+                            // reference to the value was given out since it is not initialized.
+                            unsafe { (*self.value.get()).write(value) };
+                            guard.set_state_on_drop_to = COMPLETE;
+                            Ok(())
+                        }
+                        Err(err) => {
+                            guard.set_state_on_drop_to = INCOMPLETE;
+                            Err(err)
+                        }
+                    };
+                }
+                RUNNING => {
+                    // Let the running thread know that it has to wake us up.
+                    if let Err(s) = self.state.compare_exchange_weak(
+                        RUNNING,
+                        QUEUED,
+                        Ordering::Relaxed,
+                        Ordering::Acquire,
+                    ) {
+                        state = s;
+                        continue;
+                    }
+                    futex::wait(&self.state, QUEUED);
+                    state = self.state.load(Ordering::Acquire);
+                }
+                QUEUED => {
+                    futex::wait(&self.state, QUEUED);
+                    state = self.state.load(Ordering::Acquire);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == COMPLETE {
+            // SAFETY: The value is initialized and is not accessed again.
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceLock").field(value).finish(),
+            None => f.write_str("OnceLock(<uninit>)"),
+        }
+    }
+}
+
+/// Publishes the outcome of an initializer and wakes up the threads waiting for it.
+struct CompletionGuard<'a> {
+    state: &'a AtomicU32,
+    set_state_on_drop_to: u32,
+}
+
+impl Drop for CompletionGuard<'_> {
+    fn drop(&mut self) {
+        // Synchronizes the write of the value with threads that load `COMPLETE`.
+        if self
+            .state
+            .swap(self.set_state_on_drop_to, Ordering::Release)
+            == QUEUED
+        {
+            futex::wake_all(self.state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::Arc;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn set_only_once() {
+        let mut cell = OnceLock::new();
+        assert!(cell.get().is_none());
+        assert!(cell.set(1).is_ok());
+        assert_eq!(cell.set(2), Err(2));
+        assert_eq!(cell.get(), Some(&1));
+
+        assert_eq!(cell.take(), Some(1));
+        assert!(cell.get_mut().is_none());
+        assert_eq!(cell.get_or_try_init(|| Err(())), Err(()));
+        assert_eq!(*cell.get_or_init(|| 3), 3);
+        assert_eq!(cell.into_inner(), Some(3));
+    }
+
+    #[test]
+    fn concurrent_get_or_init_runs_initializer_once() {
+        const N_THREADS: usize = 16;
+
+        let cell = Arc::new(OnceLock::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(N_THREADS));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|i| {
+                let cell = cell.clone();
+                let calls = calls.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    let value = *cell.get_or_init(|| {
+                        calls.fetch_add(1, Ordering::Relaxed);
+                        // Keep the other threads waiting for a while.
+                        thread::sleep(std::time::Duration::from_millis(10));
+                        i
+                    });
+                    assert_eq!(cell.get(), Some(&value));
+                    value
+                })
+            })
+            .collect();
+
+        let values: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(values.iter().all(|&v| v == values[0]));
+    }
+
+    #[test]
+    fn panicking_initializer_lets_the_next_one_run() {
+        let cell = OnceLock::new();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            cell.get_or_init(|| -> i32 { panic!("init failed") })
+        }));
+        assert!(res.is_err());
+        assert!(cell.get().is_none());
+        assert_eq!(*cell.get_or_init(|| 1), 1);
+    }
+}
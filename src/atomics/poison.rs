@@ -0,0 +1,150 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The result of a locking method that can fail because the lock is poisoned.
+pub type LockResult<G> = Result<G, PoisonError<G>>;
+
+/// The result of a non-blocking locking method.
+pub type TryLockResult<G> = Result<G, TryLockError<G>>;
+
+/// An error returned when acquiring a lock whose previous holder panicked while holding it.
+///
+/// The data protected by the lock might have been left in an inconsistent state. The error still
+/// carries whatever the locking method would have returned, so the caller can decide to recover
+/// with [`PoisonError::into_inner`].
+pub struct PoisonError<G> {
+    guard: G,
+}
+
+impl<G> PoisonError<G> {
+    /// Wrap the value that would have been returned if the lock was not poisoned.
+    pub fn new(guard: G) -> Self {
+        Self { guard }
+    }
+
+    /// Consume the error and return the value it carries, ignoring the poisoning.
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+
+    /// Get a reference to the value carried by the error.
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+
+    /// Get a mutable reference to the value carried by the error.
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+}
+
+impl<G> fmt::Debug for PoisonError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<G> fmt::Display for PoisonError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("poisoned lock: another thread panicked while holding it")
+    }
+}
+
+impl<G> Error for PoisonError<G> {}
+
+/// An error returned when a lock could not be acquired without blocking.
+pub enum TryLockError<G> {
+    /// The lock was acquired, but it is poisoned.
+    Poisoned(PoisonError<G>),
+    /// The lock is held by someone else.
+    WouldBlock,
+}
+
+impl<G> From<PoisonError<G>> for TryLockError<G> {
+    fn from(err: PoisonError<G>) -> Self {
+        Self::Poisoned(err)
+    }
+}
+
+impl<G> fmt::Debug for TryLockError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Poisoned(err) => f.debug_tuple("Poisoned").field(err).finish(),
+            Self::WouldBlock => f.write_str("WouldBlock"),
+        }
+    }
+}
+
+impl<G> fmt::Display for TryLockError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Poisoned(err) => err.fmt(f),
+            Self::WouldBlock => f.write_str("try_lock failed because the operation would block"),
+        }
+    }
+}
+
+impl<G> Error for TryLockError<G> {}
+
+/// Whether a lock is poisoned. A lock gets poisoned when a thread panics while holding it.
+#[derive(Debug)]
+pub(crate) struct Flag {
+    failed: AtomicBool,
+}
+
+impl Flag {
+    pub(crate) const fn new() -> Self {
+        Self {
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Called right after acquiring the lock, the returned guard must be passed to [`Flag::done`]
+    /// right before releasing the lock.
+    pub(crate) fn guard(&self) -> LockResult<Guard> {
+        let guard = Guard {
+            panicking: std::thread::panicking(),
+        };
+        if self.get() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /// Poison the lock if the current thread started panicking while holding it.
+    pub(crate) fn done(&self, guard: &Guard) {
+        // A thread that was already panicking when it took the lock, e.g. in a `Drop`
+        // implementation, did not leave the data in a bad state by panicking.
+        if !guard.panicking && std::thread::panicking() {
+            self.failed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn get(&self) -> bool {
+        // Relaxed is enough since the lock itself synchronizes the threads.
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.failed.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Remembers whether the thread holding a lock was already panicking when it acquired it.
+#[derive(Debug)]
+pub(crate) struct Guard {
+    panicking: bool,
+}
+
+/// Turn the result of acquiring the poison guard into the result of a locking method.
+pub(crate) fn map_result<T, U, F>(result: LockResult<T>, f: F) -> LockResult<U>
+where
+    F: FnOnce(T) -> U,
+{
+    match result {
+        Ok(t) => Ok(f(t)),
+        Err(err) => Err(PoisonError::new(f(err.into_inner()))),
+    }
+}
@@ -0,0 +1,277 @@
+use super::epoch;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+struct Node<T> {
+    /// Uninitialized for the sentinel node at the head of the queue, whose value has either been
+    /// popped or never existed.
+    value: MaybeUninit<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn sentinel() -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            value: MaybeUninit::uninit(),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+/// A lock-free multi-producer multi-consumer FIFO queue, following the algorithm of Michael and
+/// Scott.
+///
+/// The queue is a singly-linked list whose first node is a sentinel. Values are pushed by linking
+/// a node after the last one, and popped by moving the head to the next node, whose value is
+/// taken and which becomes the new sentinel. Both ends are updated with compare-and-swap, and a
+/// thread that finds the tail lagging behind helps moving it forward instead of waiting.
+///
+/// Popped nodes are freed through [epoch](super::epoch) based reclamation, since other threads
+/// might still be reading them.
+#[derive(Debug)]
+pub struct Queue<T> {
+    head: AtomicPtr<Node<T>>,
+    tail: AtomicPtr<Node<T>>,
+}
+
+// SAFETY: Values are moved in and out of the queue by value, so sending them between threads is
+// all that sharing the queue does.
+unsafe impl<T> Send for Queue<T> where T: Send {}
+
+// SAFETY: Same as above, a shared queue gives no reference to its values.
+unsafe impl<T> Sync for Queue<T> where T: Send {}
+
+impl<T> Queue<T> {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        let sentinel = Node::sentinel();
+        Self {
+            head: AtomicPtr::new(sentinel),
+            tail: AtomicPtr::new(sentinel),
+        }
+    }
+
+    /// Add a value to the back of the queue.
+    pub fn push(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node {
+            value: MaybeUninit::new(value),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }));
+        let _guard = epoch::pin();
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            // SAFETY: Nodes are only freed once they are unreachable and no thread that was pinned
+            // when they were reachable is still pinned.
+            let next = unsafe { &(*tail).next };
+            let next_ptr = next.load(Ordering::Acquire);
+            if !next_ptr.is_null() {
+                // The tail is lagging behind, help moving it forward.
+                let _ = self.tail.compare_exchange(
+                    tail,
+                    next_ptr,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
+                continue;
+            }
+            // Release makes the value visible to the thread that pops it.
+            if next
+                .compare_exchange(ptr::null_mut(), node, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                // Failing is fine, it means that another thread has already moved the tail.
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, node, Ordering::Release, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    /// Remove the value at the front of the queue, returns `None` if the queue is empty.
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            // SAFETY: The head is reachable, or was when we loaded it while pinned.
+            let next = unsafe { (*head).next.load(Ordering::Acquire) };
+            if next.is_null() {
+                return None;
+            }
+            let tail = self.tail.load(Ordering::Acquire);
+            if head == tail {
+                // The tail must not fall behind the head, otherwise it would point to a node that
+                // can be freed.
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+                continue;
+            }
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                // SAFETY: We won the race for this node, so we are the only thread that takes its
+                // value. The node now acts as the sentinel, whose value is never read again.
+                let value = unsafe { (*next).value.assume_init_read() };
+                // SAFETY: The old sentinel is no longer reachable from the queue. Its value has
+                // already been taken, so freeing it does not drop any `T`.
+                unsafe { guard.defer_destroy(head) };
+                return Some(value);
+            }
+        }
+    }
+
+    /// Return whether the queue is empty. The answer might be outdated as soon as it is returned.
+    pub fn is_empty(&self) -> bool {
+        let _guard = epoch::pin();
+        let head = self.head.load(Ordering::Acquire);
+        // SAFETY: The head is reachable, or was when we loaded it while pinned.
+        unsafe { (*head).next.load(Ordering::Acquire).is_null() }
+    }
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        // We have exclusive access to the queue, so the nodes can be freed right away.
+        let sentinel = *self.head.get_mut();
+        // SAFETY: The sentinel is owned by the queue, and its value is uninitialized.
+        let mut node = unsafe { Box::from_raw(sentinel) };
+        loop {
+            let next = *node.next.get_mut();
+            if next.is_null() {
+                break;
+            }
+            // SAFETY: Every node after the sentinel is owned by the queue and holds a value.
+            node = unsafe { Box::from_raw(next) };
+            // SAFETY: The value is dropped once, before the node is freed.
+            unsafe { node.value.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::{Arc, Mutex};
+    use crate::collections::DoublyLinkedList;
+    use std::collections::HashSet;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn values_come_out_in_order() {
+        let queue = Queue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+        for i in 0..10 {
+            queue.push(i);
+        }
+        assert!(!queue.is_empty());
+        assert_eq!(
+            (0..10).map(|_| queue.pop().unwrap()).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn remaining_values_are_dropped_with_the_queue() {
+        let value = Arc::new(());
+        let queue = Queue::new();
+        for _ in 0..3 {
+            queue.push(value.clone());
+        }
+        drop(queue.pop());
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(queue);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    /// Run `n_threads` producers and as many consumers on `push` and `pop`, each consumer returns
+    /// the values it has popped.
+    fn stress<Q, P, C>(queue: Q, n_threads: usize, n_items: usize, push: P, pop: C) -> Vec<usize>
+    where
+        Q: Send + Sync + 'static,
+        P: Fn(&Q, usize) + Send + Sync + Copy + 'static,
+        C: Fn(&Q) -> Option<usize> + Send + Sync + Copy + 'static,
+    {
+        let queue = Arc::new(queue);
+        let producers: Vec<_> = (0..n_threads)
+            .map(|t| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    for i in 0..n_items {
+                        push(&queue, t * n_items + i);
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..n_threads)
+            .map(|_| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    let mut popped = Vec::with_capacity(n_items);
+                    while popped.len() < n_items {
+                        match pop(&queue) {
+                            Some(v) => popped.push(v),
+                            None => std::hint::spin_loop(),
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+        for h in producers {
+            h.join().unwrap();
+        }
+        consumers
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_push_and_pop() {
+        const N_THREADS: usize = 4;
+        const N_ITEMS: usize = 10_000;
+
+        let popped = stress(Queue::new(), N_THREADS, N_ITEMS, Queue::push, Queue::pop);
+        let unique: HashSet<_> = popped.iter().collect();
+        assert_eq!(unique.len(), N_THREADS * N_ITEMS);
+    }
+
+    #[test]
+    #[ignore = "Benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn throughput_against_locked_list() {
+        const N_THREADS: usize = 4;
+        const N_ITEMS: usize = 1_000_000;
+
+        fn report(name: &str, elapsed: Duration) {
+            let ops = (2 * N_THREADS * N_ITEMS) as f64 / elapsed.as_secs_f64();
+            println!("{name}: {elapsed:?} ({:.1} Mops/s)", ops / 1e6);
+        }
+
+        let start = Instant::now();
+        stress(Queue::new(), N_THREADS, N_ITEMS, Queue::push, Queue::pop);
+        report("Queue", start.elapsed());
+
+        let start = Instant::now();
+        stress(
+            Mutex::new(DoublyLinkedList::new()),
+            N_THREADS,
+            N_ITEMS,
+            |list, v| list.lock().push_back(v),
+            |list| list.lock().pop_front(),
+        );
+        report("Mutex<DoublyLinkedList>", start.elapsed());
+    }
+}
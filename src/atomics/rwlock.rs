@@ -0,0 +1,836 @@
+use super::futex;
+use super::poison::{self, LockResult, PoisonError, TryLockError, TryLockResult};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The lock is held by a writer.
+const WRITE_LOCKED: u32 = 1;
+/// A writer, or an upgradable reader trying to upgrade, is waiting for the lock. New readers back
+/// off while this is set, so that writers do not starve.
+const WRITER_WAITING: u32 = 1 << 1;
+/// The lock is held by an upgradable reader.
+const UPGRADABLE: u32 = 1 << 2;
+/// The amount added to the state for each reader holding the lock, readers are counted in the
+/// remaining bits.
+const READER: u32 = 1 << 3;
+const READERS_MASK: u32 = !(READER - 1);
+
+/// A reader-writer lock, allowing either many readers or a single writer to access the shared
+/// data at the same time.
+///
+/// The lock prefers writers: once a writer is waiting, new readers wait until it is done, so that
+/// a steady stream of readers cannot starve the writers.
+///
+/// Besides plain readers, the lock can be held by a single upgradable reader alongside the plain
+/// readers. The upgradable reader can later be promoted to a writer without releasing the lock,
+/// so no other writer can get in between.
+///
+/// If a thread panics while holding the lock, the lock is released while unwinding, like with
+/// [`Mutex`](crate::atomics::Mutex). Use a [`PoisoningRwLock`] to find out about such panics
+/// instead.
+#[derive(Debug)]
+pub struct RwLock<T> {
+    // Holds the flags above and the number of readers. Every thread blocked on the lock waits on
+    // this word, and is woken whenever a change could let it through.
+    state: AtomicU32,
+    v: UnsafeCell<T>,
+}
+
+// SAFETY: Readers on multiple threads can access the value at the same time, so `T` must be
+// [`Sync`]. A writer on any thread can mutate the value, so `T` must also be [`Send`].
+unsafe impl<T> Sync for RwLock<T> where T: Send + Sync {}
+
+impl<T> RwLock<T> {
+    /// Wrap the given value in a `RwLock` to provide safe concurrent accesses from multiple
+    /// threads.
+    pub fn new(t: T) -> Self {
+        Self {
+            state: AtomicU32::new(0),
+            v: UnsafeCell::new(t),
+        }
+    }
+
+    /// Acquire shared access to the inner value, blocking the current thread while a writer holds
+    /// the lock or is waiting for it.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let mut s = self.state.load(Ordering::Relaxed);
+        loop {
+            if s & (WRITE_LOCKED | WRITER_WAITING) == 0 {
+                assert!(s < READERS_MASK, "too many readers");
+                match self.state.compare_exchange_weak(
+                    s,
+                    s + READER,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return self.read_guard(),
+                    Err(e) => s = e,
+                }
+            } else {
+                futex::wait(&self.state, s);
+                s = self.state.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Attempt to acquire shared access to the inner value without waiting, returns `None` if a
+    /// writer holds the lock or is waiting for it.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut s = self.state.load(Ordering::Relaxed);
+        while s & (WRITE_LOCKED | WRITER_WAITING) == 0 {
+            assert!(s < READERS_MASK, "too many readers");
+            match self.state.compare_exchange_weak(
+                s,
+                s + READER,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(self.read_guard()),
+                Err(e) => s = e,
+            }
+        }
+        None
+    }
+
+    /// Acquire shared access to the inner value with the possibility of upgrading to exclusive
+    /// access later. Only one upgradable reader can hold the lock at a time, but it can do so
+    /// alongside plain readers.
+    pub fn upgradable_read(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        let mut s = self.state.load(Ordering::Relaxed);
+        loop {
+            if s & (WRITE_LOCKED | WRITER_WAITING | UPGRADABLE) == 0 {
+                match self.state.compare_exchange_weak(
+                    s,
+                    s | UPGRADABLE,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return self.upgradable_read_guard(),
+                    Err(e) => s = e,
+                }
+            } else {
+                futex::wait(&self.state, s);
+                s = self.state.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Attempt to acquire upgradable shared access to the inner value without waiting, returns
+    /// `None` if a writer or another upgradable reader holds the lock, or if a writer is waiting
+    /// for it.
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        let mut s = self.state.load(Ordering::Relaxed);
+        while s & (WRITE_LOCKED | WRITER_WAITING | UPGRADABLE) == 0 {
+            match self.state.compare_exchange_weak(
+                s,
+                s | UPGRADABLE,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(self.upgradable_read_guard()),
+                Err(e) => s = e,
+            }
+        }
+        None
+    }
+
+    /// Acquire exclusive access to the inner value, blocking the current thread until there is no
+    /// other reader or writer.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let mut s = self.state.load(Ordering::Relaxed);
+        loop {
+            if s & !WRITER_WAITING == 0 {
+                // Nobody is holding the lock. Waiting writers set the flag again when they wake
+                // up and find the lock held.
+                match self.state.compare_exchange_weak(
+                    s,
+                    WRITE_LOCKED,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return self.write_guard(),
+                    Err(e) => s = e,
+                }
+            } else if s & WRITER_WAITING == 0 {
+                // Stop new readers from coming in before we go to sleep.
+                match self.state.compare_exchange_weak(
+                    s,
+                    s | WRITER_WAITING,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => s |= WRITER_WAITING,
+                    Err(e) => s = e,
+                }
+            } else {
+                futex::wait(&self.state, s);
+                s = self.state.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Attempt to acquire exclusive access to the inner value without waiting, returns `None` if
+    /// the lock is held.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let mut s = self.state.load(Ordering::Relaxed);
+        while s & !WRITER_WAITING == 0 {
+            match self.state.compare_exchange_weak(
+                s,
+                WRITE_LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(self.write_guard()),
+                Err(e) => s = e,
+            }
+        }
+        None
+    }
+
+    /// Get a mutable reference to the inner value. No locking is needed since the mutable borrow
+    /// guarantees that there is no other access to the `RwLock`.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.v.get_mut()
+    }
+
+    /// Consume the `RwLock` and return the inner value.
+    pub fn into_inner(self) -> T {
+        self.v.into_inner()
+    }
+
+    fn read_guard(&self) -> RwLockReadGuard<'_, T> {
+        RwLockReadGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+
+    fn upgradable_read_guard(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        RwLockUpgradableReadGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+
+    fn write_guard(&self) -> RwLockWriteGuard<'_, T> {
+        RwLockWriteGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A guard giving shared access to the value of a read-locked [`RwLock`]. The lock is released
+/// when the guard is dropped.
+///
+/// This struct is created by [`RwLock::read()`] and [`RwLock::try_read()`].
+#[derive(Debug)]
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    marker: PhantomData<&'a T>,
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We are holding a read lock, so there is no writer.
+        unsafe { &*self.lock.v.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        let s = self.lock.state.fetch_sub(READER, Ordering::Release) - READER;
+        // Wake up the writer or the upgradable reader waiting for the readers to leave.
+        if s & READERS_MASK == 0 && s & WRITER_WAITING != 0 {
+            futex::wake_all(&self.lock.state);
+        }
+    }
+}
+
+/// A guard giving shared access to the value of an [`RwLock`], which can be upgraded to exclusive
+/// access. The lock is released when the guard is dropped.
+///
+/// This struct is created by [`RwLock::upgradable_read()`] and
+/// [`RwLock::try_upgradable_read()`].
+#[derive(Debug)]
+pub struct RwLockUpgradableReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> RwLockUpgradableReadGuard<'a, T> {
+    /// Atomically promote the guard to exclusive access, blocking the current thread until the
+    /// plain readers are done. No writer can acquire the lock in the meantime.
+    pub fn upgrade(this: Self) -> RwLockWriteGuard<'a, T> {
+        let lock = this.lock;
+        // The lock stays held, we just change how it is held.
+        std::mem::forget(this);
+
+        let mut s = lock.state.load(Ordering::Relaxed);
+        loop {
+            if s & READERS_MASK == 0 {
+                match lock.state.compare_exchange_weak(
+                    s,
+                    WRITE_LOCKED,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => break,
+                    Err(e) => s = e,
+                }
+            } else if s & WRITER_WAITING == 0 {
+                // Stop new readers from coming in before we go to sleep.
+                match lock.state.compare_exchange_weak(
+                    s,
+                    s | WRITER_WAITING,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => s |= WRITER_WAITING,
+                    Err(e) => s = e,
+                }
+            } else {
+                futex::wait(&lock.state, s);
+                s = lock.state.load(Ordering::Relaxed);
+            }
+        }
+        lock.write_guard()
+    }
+
+    /// Attempt to promote the guard to exclusive access without waiting, returns the guard back
+    /// if there are plain readers holding the lock.
+    pub fn try_upgrade(this: Self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        let lock = this.lock;
+        let mut s = lock.state.load(Ordering::Relaxed);
+        while s & READERS_MASK == 0 {
+            match lock.state.compare_exchange_weak(
+                s,
+                WRITE_LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    std::mem::forget(this);
+                    return Ok(lock.write_guard());
+                }
+                Err(e) => s = e,
+            }
+        }
+        Err(this)
+    }
+}
+
+impl<T> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We are holding an upgradable read lock, so there is no writer.
+        unsafe { &*self.lock.v.get() }
+    }
+}
+
+impl<T> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_and(!UPGRADABLE, Ordering::Release);
+        // Wake up the writers and the upgradable readers that were blocked by us.
+        futex::wake_all(&self.lock.state);
+    }
+}
+
+/// A guard giving exclusive access to the value of a write-locked [`RwLock`]. The lock is
+/// released when the guard is dropped.
+///
+/// This struct is created by [`RwLock::write()`], [`RwLock::try_write()`], and by upgrading a
+/// [`RwLockUpgradableReadGuard`].
+#[derive(Debug)]
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We are holding the write lock.
+        unsafe { &*self.lock.v.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: We are holding the write lock.
+        unsafe { &mut *self.lock.v.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.store(0, Ordering::Release);
+        // Wake up everyone, the waiting writers set their flag again if they lose the race.
+        futex::wake_all(&self.lock.state);
+    }
+}
+
+/// A [`RwLock`] that gets poisoned when a thread panics while holding its write lock.
+///
+/// Once poisoned, every following attempt to acquire the lock returns a [`PoisonError`], since the
+/// panicking writer might have left the inner value in an inconsistent state. Readers cannot
+/// modify the value, so a panic while holding a read lock does not poison it. The lock itself is
+/// always released, so other threads can choose to ignore the poisoning, or clear it with
+/// [`PoisoningRwLock::clear_poison`] once they have restored the inner value.
+///
+/// # Examples
+///
+/// ```
+/// use rusty_crust::atomics::PoisoningRwLock;
+/// use std::panic::{self, AssertUnwindSafe};
+///
+/// let l = PoisoningRwLock::new(0);
+/// let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+///     let mut w = l.write().unwrap();
+///     *w += 1;
+///     panic!("oops");
+/// }));
+///
+/// assert!(l.is_poisoned());
+/// let r = l.read().unwrap_or_else(|err| err.into_inner());
+/// assert_eq!(*r, 1);
+/// ```
+#[derive(Debug)]
+pub struct PoisoningRwLock<T> {
+    lock: RwLock<T>,
+    poison: poison::Flag,
+}
+
+impl<T> PoisoningRwLock<T> {
+    /// Wrap the given value in a `PoisoningRwLock` to provide safe concurrent accesses from
+    /// multiple threads.
+    pub fn new(t: T) -> Self {
+        Self {
+            lock: RwLock::new(t),
+            poison: poison::Flag::new(),
+        }
+    }
+
+    /// Acquire shared access to the inner value, blocking the current thread while a writer holds
+    /// the lock or is waiting for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the guard if the lock is poisoned.
+    pub fn read(&self) -> LockResult<PoisoningRwLockReadGuard<'_, T>> {
+        self.check(PoisoningRwLockReadGuard {
+            guard: self.lock.read(),
+        })
+    }
+
+    /// Attempt to acquire shared access to the inner value without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TryLockError::WouldBlock`] if a writer holds the lock or is waiting for it, and
+    /// [`TryLockError::Poisoned`] holding the guard if the lock is poisoned.
+    pub fn try_read(&self) -> TryLockResult<PoisoningRwLockReadGuard<'_, T>> {
+        let guard = self.lock.try_read().ok_or(TryLockError::WouldBlock)?;
+        Ok(self.check(PoisoningRwLockReadGuard { guard })?)
+    }
+
+    /// Acquire shared access to the inner value with the possibility of upgrading to exclusive
+    /// access later, see [`RwLock::upgradable_read`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the guard if the lock is poisoned.
+    pub fn upgradable_read(&self) -> LockResult<PoisoningRwLockUpgradableReadGuard<'_, T>> {
+        self.check(PoisoningRwLockUpgradableReadGuard {
+            guard: self.lock.upgradable_read(),
+            flag: &self.poison,
+        })
+    }
+
+    /// Attempt to acquire upgradable shared access to the inner value without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TryLockError::WouldBlock`] if a writer or another upgradable reader holds the
+    /// lock, or if a writer is waiting for it, and [`TryLockError::Poisoned`] holding the guard if
+    /// the lock is poisoned.
+    pub fn try_upgradable_read(&self) -> TryLockResult<PoisoningRwLockUpgradableReadGuard<'_, T>> {
+        let guard = self
+            .lock
+            .try_upgradable_read()
+            .ok_or(TryLockError::WouldBlock)?;
+        Ok(self.check(PoisoningRwLockUpgradableReadGuard {
+            guard,
+            flag: &self.poison,
+        })?)
+    }
+
+    /// Acquire exclusive access to the inner value, blocking the current thread until there is no
+    /// other reader or writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the guard if the lock is poisoned.
+    pub fn write(&self) -> LockResult<PoisoningRwLockWriteGuard<'_, T>> {
+        PoisoningRwLockWriteGuard::new(self.lock.write(), &self.poison)
+    }
+
+    /// Attempt to acquire exclusive access to the inner value without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TryLockError::WouldBlock`] if the lock is held, and [`TryLockError::Poisoned`]
+    /// holding the guard if the lock is poisoned.
+    pub fn try_write(&self) -> TryLockResult<PoisoningRwLockWriteGuard<'_, T>> {
+        let guard = self.lock.try_write().ok_or(TryLockError::WouldBlock)?;
+        Ok(PoisoningRwLockWriteGuard::new(guard, &self.poison)?)
+    }
+
+    /// Returns true if a thread panicked while holding the write lock.
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+
+    /// Clear the poisoned state of the lock, so that it can be acquired without errors again.
+    pub fn clear_poison(&self) {
+        self.poison.clear();
+    }
+
+    /// Get a mutable reference to the inner value. No locking is needed since the mutable borrow
+    /// guarantees that there is no other access to the `PoisoningRwLock`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the reference if the lock is poisoned.
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let poisoned = self.poison.get();
+        let v = self.lock.get_mut();
+        if poisoned {
+            Err(PoisonError::new(v))
+        } else {
+            Ok(v)
+        }
+    }
+
+    /// Consume the `PoisoningRwLock` and return the inner value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoisonError`] holding the value if the lock is poisoned.
+    pub fn into_inner(self) -> LockResult<T> {
+        let poisoned = self.poison.get();
+        let v = self.lock.into_inner();
+        if poisoned {
+            Err(PoisonError::new(v))
+        } else {
+            Ok(v)
+        }
+    }
+
+    /// Report the poisoning to a reader that has just acquired the lock.
+    fn check<G>(&self, guard: G) -> LockResult<G> {
+        if self.poison.get() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+}
+
+/// A guard giving shared access to the value of a read-locked [`PoisoningRwLock`]. The lock is
+/// released when the guard is dropped.
+///
+/// This struct is created by [`PoisoningRwLock::read()`] and [`PoisoningRwLock::try_read()`].
+#[derive(Debug)]
+pub struct PoisoningRwLockReadGuard<'a, T> {
+    guard: RwLockReadGuard<'a, T>,
+}
+
+impl<T> Deref for PoisoningRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+/// A guard giving shared access to the value of a [`PoisoningRwLock`], which can be upgraded to
+/// exclusive access. The lock is released when the guard is dropped.
+///
+/// This struct is created by [`PoisoningRwLock::upgradable_read()`] and
+/// [`PoisoningRwLock::try_upgradable_read()`].
+#[derive(Debug)]
+pub struct PoisoningRwLockUpgradableReadGuard<'a, T> {
+    guard: RwLockUpgradableReadGuard<'a, T>,
+    flag: &'a poison::Flag,
+}
+
+impl<'a, T> PoisoningRwLockUpgradableReadGuard<'a, T> {
+    /// Atomically promote the guard to exclusive access, see [`RwLockUpgradableReadGuard::upgrade`].
+    pub fn upgrade(this: Self) -> PoisoningRwLockWriteGuard<'a, T> {
+        let guard = RwLockUpgradableReadGuard::upgrade(this.guard);
+        // Poisoning was already reported when the upgradable read lock was acquired.
+        PoisoningRwLockWriteGuard::new(guard, this.flag).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Attempt to promote the guard to exclusive access without waiting, returns the guard back
+    /// if there are plain readers holding the lock.
+    pub fn try_upgrade(this: Self) -> Result<PoisoningRwLockWriteGuard<'a, T>, Self> {
+        match RwLockUpgradableReadGuard::try_upgrade(this.guard) {
+            Ok(guard) => Ok(PoisoningRwLockWriteGuard::new(guard, this.flag)
+                .unwrap_or_else(PoisonError::into_inner)),
+            Err(guard) => Err(Self {
+                guard,
+                flag: this.flag,
+            }),
+        }
+    }
+}
+
+impl<T> Deref for PoisoningRwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+/// A guard giving exclusive access to the value of a write-locked [`PoisoningRwLock`]. The lock
+/// is released when the guard is dropped, and the lock is poisoned if the thread is panicking.
+///
+/// This struct is created by [`PoisoningRwLock::write()`], [`PoisoningRwLock::try_write()`], and
+/// by upgrading a [`PoisoningRwLockUpgradableReadGuard`].
+#[derive(Debug)]
+pub struct PoisoningRwLockWriteGuard<'a, T> {
+    guard: RwLockWriteGuard<'a, T>,
+    flag: &'a poison::Flag,
+    poison: poison::Guard,
+}
+
+impl<'a, T> PoisoningRwLockWriteGuard<'a, T> {
+    /// Wrap the guard of the write lock that the current thread has just acquired.
+    fn new(guard: RwLockWriteGuard<'a, T>, flag: &'a poison::Flag) -> LockResult<Self> {
+        poison::map_result(flag.guard(), |poison| Self {
+            guard,
+            flag,
+            poison,
+        })
+    }
+}
+
+impl<T> Deref for PoisoningRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for PoisoningRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for PoisoningRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        // The inner guard is dropped right after, releasing the lock once the flag is updated.
+        self.flag.done(&self.poison);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn many_readers_or_one_writer() {
+        let l = RwLock::new(1);
+        {
+            let r1 = l.read();
+            let r2 = l.try_read().unwrap();
+            assert_eq!(*r1 + *r2, 2);
+            assert!(l.try_write().is_none());
+        }
+        {
+            let mut w = l.write();
+            *w += 1;
+            assert!(l.try_read().is_none());
+            assert!(l.try_write().is_none());
+            assert!(l.try_upgradable_read().is_none());
+        }
+        let mut l = l;
+        *l.get_mut() += 1;
+        assert_eq!(l.into_inner(), 3);
+    }
+
+    #[test]
+    fn waiting_writer_blocks_new_readers() {
+        let l = Arc::new(RwLock::new(0));
+        let r = l.read();
+        let writer = {
+            let l = Arc::clone(&l);
+            thread::spawn(move || *l.write() += 1)
+        };
+
+        // Wait for the writer to announce itself.
+        while l.state.load(Ordering::Relaxed) & WRITER_WAITING == 0 {
+            thread::yield_now();
+        }
+        assert!(l.try_read().is_none());
+        drop(r);
+
+        writer.join().unwrap();
+        assert_eq!(*l.read(), 1);
+    }
+
+    #[test]
+    fn upgradable_read_coexists_with_readers() {
+        let l = RwLock::new(vec![1]);
+        let u = l.upgradable_read();
+        let r = l.read();
+        assert_eq!(*u, *r);
+        assert!(l.try_upgradable_read().is_none());
+        assert!(l.try_write().is_none());
+
+        let u = RwLockUpgradableReadGuard::try_upgrade(u).unwrap_err();
+        drop(r);
+        let mut w = RwLockUpgradableReadGuard::try_upgrade(u).unwrap();
+        w.push(2);
+        drop(w);
+
+        assert_eq!(*l.read(), [1, 2]);
+        assert!(l.try_upgradable_read().is_some());
+    }
+
+    #[test]
+    fn upgrade_waits_for_readers() {
+        let l = Arc::new(RwLock::new(0));
+        let r = l.read();
+        let upgrader = {
+            let l = Arc::clone(&l);
+            thread::spawn(move || {
+                let u = l.upgradable_read();
+                let v = *u;
+                let mut w = RwLockUpgradableReadGuard::upgrade(u);
+                *w = v + 1;
+            })
+        };
+
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(*r, 0);
+        drop(r);
+        upgrader.join().unwrap();
+        assert_eq!(*l.read(), 1);
+    }
+
+    #[test]
+    fn concurrent_upgradable_increments() {
+        const N_THREADS: usize = 8;
+        const N_ITER: usize = 100;
+
+        let l = Arc::new(RwLock::new(0));
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|i| {
+                let l = Arc::clone(&l);
+                thread::spawn(move || {
+                    for _ in 0..N_ITER {
+                        if i % 2 == 0 {
+                            // Read, then write based on what was read, without another writer
+                            // getting in between.
+                            let u = l.upgradable_read();
+                            let v = *u;
+                            *RwLockUpgradableReadGuard::upgrade(u) = v + 1;
+                        } else {
+                            *l.write() += 1;
+                            let _ = *l.read();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(*l.read(), N_THREADS * N_ITER);
+        assert_eq!(l.state.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn panic_while_writing_releases_lock() {
+        let l = Arc::new(RwLock::new(0));
+        let handle = {
+            let l = Arc::clone(&l);
+            thread::spawn(move || {
+                let mut w = l.write();
+                *w += 1;
+                panic!("oops");
+            })
+        };
+        assert!(handle.join().is_err());
+        assert_eq!(*l.read(), 1);
+        assert_eq!(l.state.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn panic_while_writing_poisons_lock() {
+        let l = Arc::new(PoisoningRwLock::new(0));
+        let handle = {
+            let l = Arc::clone(&l);
+            thread::spawn(move || {
+                let _w = l.write().unwrap();
+                panic!("oops");
+            })
+        };
+        assert!(handle.join().is_err());
+
+        assert!(l.is_poisoned());
+        assert!(l.read().is_err());
+        assert!(matches!(l.try_write(), Err(TryLockError::Poisoned(_))));
+        assert!(matches!(
+            l.try_upgradable_read(),
+            Err(TryLockError::Poisoned(_))
+        ));
+        l.clear_poison();
+        assert!(l.write().is_ok());
+    }
+
+    #[test]
+    fn poisoning_lock_upgrades_and_ignores_panicking_readers() {
+        let mut l = PoisoningRwLock::new(vec![1]);
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _r = l.read().unwrap();
+            panic!("oops");
+        }));
+        assert!(!l.is_poisoned());
+
+        let u = l.upgradable_read().unwrap();
+        let r = l.try_read().unwrap();
+        assert!(matches!(l.try_write(), Err(TryLockError::WouldBlock)));
+        let u = PoisoningRwLockUpgradableReadGuard::try_upgrade(u).unwrap_err();
+        drop(r);
+        let mut w = PoisoningRwLockUpgradableReadGuard::upgrade(u);
+        w.push(2);
+        drop(w);
+
+        // A panic after upgrading poisons the lock.
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let u = l.upgradable_read().unwrap();
+            let _w = PoisoningRwLockUpgradableReadGuard::upgrade(u);
+            panic!("oops");
+        }));
+        assert!(l.is_poisoned());
+        assert!(l.get_mut().is_err());
+        assert_eq!(l.into_inner().unwrap_err().into_inner(), [1, 2]);
+    }
+}
@@ -0,0 +1,442 @@
+//! A bounded single-producer, single-consumer channel backed by a ring buffer.
+//!
+//! Both halves only ever write to their own index, and read the index of the other half. Each
+//! half keeps a cached copy of the other index, which is only refreshed when the buffer looks full
+//! or empty, so the halves rarely touch the same cache line. Every operation completes in a bounded
+//! number of steps, regardless of what the other half is doing.
+
+use super::Arc;
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Align a value to its own cache line, so that writing to it does not invalidate the cache line
+/// holding its neighbours. Some CPUs prefetch cache lines in pairs, hence the 128 bytes.
+#[derive(Debug)]
+#[repr(align(128))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The ring buffer shared by the two halves of a channel.
+#[derive(Debug)]
+struct Buffer<T> {
+    /// The number of values that have been popped, only written to by the consumer.
+    head: CachePadded<AtomicUsize>,
+    /// The number of values that have been pushed, only written to by the producer.
+    tail: CachePadded<AtomicUsize>,
+    /// The maximum number of values in the buffer.
+    capacity: usize,
+    /// The value at index `i` is stored at `i & (slots.len() - 1)`. Slots in `head..tail` are
+    /// initialized. The number of slots is rounded up to a power of two, so that the indices can
+    /// wrap around `usize::MAX` while still mapping to consecutive slots.
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+// SAFETY: Values are moved from the producer to the consumer, which might be on different
+// threads. A slot is only accessed by one half at a time, as determined by the indices.
+unsafe impl<T> Sync for Buffer<T> where T: Send {}
+
+impl<T> Buffer<T> {
+    /// Create a buffer whose indices both start at `start`.
+    fn new(capacity: usize, start: usize) -> Self {
+        let n_slots = capacity
+            .checked_next_power_of_two()
+            .expect("capacity overflow");
+        Self {
+            head: CachePadded(AtomicUsize::new(start)),
+            tail: CachePadded(AtomicUsize::new(start)),
+            capacity,
+            slots: (0..n_slots)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the position in `slots` of the value at `index`.
+    fn position(&self, index: usize) -> usize {
+        index & (self.slots.len() - 1)
+    }
+
+    /// Get a pointer to the slot at `position`. The pointer can be used to access the following
+    /// slots, up to the end of the buffer.
+    fn slot(&self, position: usize) -> *mut T {
+        debug_assert!(position < self.slots.len());
+        // SAFETY: The offset is in bounds. `UnsafeCell` and `MaybeUninit` have the same layout as
+        // `T`.
+        unsafe { UnsafeCell::raw_get(self.slots.as_ptr().add(position)).cast() }
+    }
+}
+
+impl<T> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        let head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        // The indices might have wrapped around, so the values are counted rather than iterated
+        // over as a range.
+        for i in 0..tail.wrapping_sub(head) {
+            // SAFETY: Slots in `head..tail` hold values that were never popped.
+            unsafe { ptr::drop_in_place(self.slot(self.position(head.wrapping_add(i)))) };
+        }
+    }
+}
+
+/// Create a channel that holds at most `capacity` values, returns its producing half and its
+/// consuming half.
+///
+/// The buffer allocates room for `capacity` rounded up to a power of two.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    channel_starting_at(capacity, 0)
+}
+
+/// Create a channel whose indices start at `start`, which lets tests reach the point where they
+/// wrap around.
+fn channel_starting_at<T>(capacity: usize, start: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "capacity must be positive");
+    let buffer = Arc::new(Buffer::new(capacity, start));
+    (
+        Producer {
+            buffer: buffer.clone(),
+            tail: start,
+            cached_head: start,
+        },
+        Consumer {
+            buffer,
+            head: start,
+            cached_tail: start,
+        },
+    )
+}
+
+/// The producing half of a [`channel`].
+#[derive(Debug)]
+pub struct Producer<T> {
+    buffer: Arc<Buffer<T>>,
+    /// Our own copy of `buffer.tail`, which only we write to.
+    tail: usize,
+    /// The last value of `buffer.head` that we have read.
+    cached_head: usize,
+}
+
+impl<T> Producer<T> {
+    /// Push a value, the value is given back if the buffer is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.reserve(1) == 0 {
+            return Err(value);
+        }
+        // SAFETY: The slot is outside of `head..tail`, so the consumer does not access it.
+        unsafe {
+            self.buffer
+                .slot(self.buffer.position(self.tail))
+                .write(value)
+        };
+        self.tail = self.tail.wrapping_add(1);
+        // Release makes the value visible to the consumer.
+        self.buffer.tail.store(self.tail, Ordering::Release);
+        Ok(())
+    }
+
+    /// Push as many values from `values` as the buffer can hold, returns how many were pushed.
+    pub fn push_slice(&mut self, values: &[T]) -> usize
+    where
+        T: Copy,
+    {
+        let n = self.reserve(values.len());
+        let start = self.buffer.position(self.tail);
+        // The free slots might wrap around the end of the buffer.
+        let first = n.min(self.buffer.slots.len() - start);
+        // SAFETY: The `n` slots after `tail` are outside of `head..tail`, so the consumer does not
+        // access them. `T` is `Copy`, so overwriting the slots does not leak anything.
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr(), self.buffer.slot(start), first);
+            ptr::copy_nonoverlapping(values[first..].as_ptr(), self.buffer.slot(0), n - first);
+        }
+        self.tail = self.tail.wrapping_add(n);
+        self.buffer.tail.store(self.tail, Ordering::Release);
+        n
+    }
+
+    /// Return the number of values that can be pushed without the buffer being full.
+    pub fn free_slots(&mut self) -> usize {
+        self.reserve(self.buffer.capacity())
+    }
+
+    /// Return how many of `wanted` slots are free, the index of the consumer is only read if the
+    /// cached one does not leave enough of them.
+    fn reserve(&mut self, wanted: usize) -> usize {
+        let capacity = self.buffer.capacity();
+        if capacity - self.tail.wrapping_sub(self.cached_head) < wanted {
+            // Acquire makes sure that the consumer is done with the slots it has popped.
+            self.cached_head = self.buffer.head.load(Ordering::Acquire);
+        }
+        wanted.min(capacity - self.tail.wrapping_sub(self.cached_head))
+    }
+
+    /// Return the maximum number of values in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
+
+/// The consuming half of a [`channel`].
+#[derive(Debug)]
+pub struct Consumer<T> {
+    buffer: Arc<Buffer<T>>,
+    /// Our own copy of `buffer.head`, which only we write to.
+    head: usize,
+    /// The last value of `buffer.tail` that we have read.
+    cached_tail: usize,
+}
+
+impl<T> Consumer<T> {
+    /// Pop the oldest value, returns `None` if the buffer is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.acquire(1) == 0 {
+            return None;
+        }
+        // SAFETY: The slot is inside of `head..tail`, so it is initialized and the producer does
+        // not access it.
+        let value = unsafe { self.buffer.slot(self.buffer.position(self.head)).read() };
+        self.head = self.head.wrapping_add(1);
+        // Release lets the producer reuse the slot only once we are done reading it.
+        self.buffer.head.store(self.head, Ordering::Release);
+        Some(value)
+    }
+
+    /// Pop as many values as `values` can hold, returns how many were popped.
+    pub fn pop_slice(&mut self, values: &mut [T]) -> usize
+    where
+        T: Copy,
+    {
+        let n = self.acquire(values.len());
+        let start = self.buffer.position(self.head);
+        // The values might wrap around the end of the buffer.
+        let first = n.min(self.buffer.slots.len() - start);
+        // SAFETY: The `n` slots after `head` are inside of `head..tail`, so they are initialized
+        // and the producer does not access them.
+        unsafe {
+            ptr::copy_nonoverlapping(self.buffer.slot(start), values.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(self.buffer.slot(0), values[first..].as_mut_ptr(), n - first);
+        }
+        self.head = self.head.wrapping_add(n);
+        self.buffer.head.store(self.head, Ordering::Release);
+        n
+    }
+
+    /// Return the number of values that can be popped.
+    pub fn len(&mut self) -> usize {
+        self.acquire(usize::MAX)
+    }
+
+    /// Return how many of `wanted` values can be popped, the index of the producer is only read
+    /// if the cached one does not give enough of them.
+    fn acquire(&mut self, wanted: usize) -> usize {
+        if self.cached_tail.wrapping_sub(self.head) < wanted {
+            // Acquire makes the values pushed by the producer visible.
+            self.cached_tail = self.buffer.tail.load(Ordering::Acquire);
+        }
+        wanted.min(self.cached_tail.wrapping_sub(self.head))
+    }
+
+    /// Return whether there is no value to pop.
+    pub fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the maximum number of values in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::Mutex;
+    use std::collections::VecDeque;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn push_until_full_then_pop() {
+        let (mut tx, mut rx) = channel(3);
+        assert_eq!(rx.pop(), None);
+        for i in 0..3 {
+            tx.push(i).unwrap();
+        }
+        assert_eq!(tx.push(3), Err(3));
+        assert_eq!(rx.len(), 3);
+        assert_eq!(rx.pop(), Some(0));
+        tx.push(3).unwrap();
+        assert_eq!(
+            std::iter::from_fn(|| rx.pop()).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn slices_wrap_around_the_buffer() {
+        let (mut tx, mut rx) = channel(4);
+        assert_eq!(tx.push_slice(&[1, 2, 3]), 3);
+        let mut out = [0; 2];
+        assert_eq!(rx.pop_slice(&mut out), 2);
+        assert_eq!(out, [1, 2]);
+
+        // Starts at the end of the buffer and continues at its beginning.
+        assert_eq!(tx.push_slice(&[4, 5, 6, 7]), 3);
+        assert_eq!(tx.free_slots(), 0);
+        let mut out = [0; 8];
+        assert_eq!(rx.pop_slice(&mut out), 4);
+        assert_eq!(out[..4], [3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn unread_values_are_dropped() {
+        let value = Arc::new(());
+        let (mut tx, mut rx) = channel(4);
+        for _ in 0..3 {
+            tx.push(value.clone()).unwrap();
+        }
+        drop(rx.pop());
+        drop(tx);
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn indices_wrap_around_usize_max() {
+        let (mut tx, mut rx) = channel_starting_at(3, usize::MAX - 1);
+        for i in 0..3 {
+            tx.push(i).unwrap();
+        }
+        assert_eq!(tx.push(3), Err(3));
+        assert_eq!(rx.pop(), Some(0));
+        tx.push(3).unwrap();
+        assert_eq!(
+            std::iter::from_fn(|| rx.pop()).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+
+        // The tail has wrapped around but the head has not.
+        let value = Arc::new(());
+        let (mut tx, mut rx) = channel_starting_at(3, usize::MAX - 1);
+        for _ in 0..3 {
+            tx.push(value.clone()).unwrap();
+        }
+        drop(rx.pop());
+        drop((tx, rx));
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let (mut tx, mut rx) = channel_starting_at(5, usize::MAX - 2);
+        let mut out = [0; 5];
+        for round in 0..4 {
+            let values: Vec<_> = (round * 10..round * 10 + 4).collect();
+            assert_eq!(tx.push_slice(&values), 4);
+            assert_eq!(rx.pop_slice(&mut out), 4);
+            assert_eq!(out[..4], values);
+        }
+    }
+
+    #[test]
+    fn values_arrive_in_order_across_threads() {
+        const N_ITEMS: usize = 100_000;
+
+        let (mut tx, mut rx) = channel(64);
+        let producer = thread::spawn(move || {
+            let mut i = 0;
+            while i < N_ITEMS {
+                match tx.push(i) {
+                    Ok(()) => i += 1,
+                    Err(_) => thread::yield_now(),
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < N_ITEMS {
+            match rx.pop() {
+                Some(i) => {
+                    assert_eq!(i, expected);
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+    }
+
+    #[test]
+    #[ignore = "Benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn throughput_against_mutex() {
+        const CAPACITY: usize = 1024;
+        const N_ITEMS: usize = 1_000_000;
+
+        fn report(name: &str, elapsed: Duration) {
+            let ops = N_ITEMS as f64 / elapsed.as_secs_f64();
+            println!("{name}: {elapsed:?} ({:.1} Mops/s)", ops / 1e6);
+        }
+
+        let start = Instant::now();
+        let (mut tx, mut rx) = channel(CAPACITY);
+        let producer = thread::spawn(move || {
+            for i in 0..N_ITEMS {
+                while tx.push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        for _ in 0..N_ITEMS {
+            while rx.pop().is_none() {
+                thread::yield_now();
+            }
+        }
+        producer.join().unwrap();
+        report("spsc", start.elapsed());
+
+        let start = Instant::now();
+        let buffer = Arc::new(Mutex::new(VecDeque::with_capacity(CAPACITY)));
+        let producer = {
+            let buffer = buffer.clone();
+            thread::spawn(move || {
+                let mut i = 0;
+                while i < N_ITEMS {
+                    let mut buffer = buffer.lock();
+                    if buffer.len() < CAPACITY {
+                        buffer.push_back(i);
+                        i += 1;
+                    } else {
+                        drop(buffer);
+                        thread::yield_now();
+                    }
+                }
+            })
+        };
+        let mut n = 0;
+        while n < N_ITEMS {
+            if buffer.lock().pop_front().is_some() {
+                n += 1;
+            } else {
+                thread::yield_now();
+            }
+        }
+        producer.join().unwrap();
+        report("Mutex<VecDeque>", start.elapsed());
+    }
+}
@@ -0,0 +1,191 @@
+use super::epoch;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+struct Node<T> {
+    /// Taken by the thread that pops the node, so it must not be dropped with the node.
+    value: ManuallyDrop<T>,
+    next: *mut Node<T>,
+}
+
+/// A lock-free stack, following the algorithm of Treiber.
+///
+/// The stack is a singly-linked list. Values are pushed and popped by swapping the head with
+/// compare-and-swap, retrying if another thread changed it in the meantime.
+///
+/// # The ABA problem
+///
+/// A naive pop loads the head `A` and its next node `B`, then swaps the head from `A` to `B`.
+/// Between the load and the swap, other threads can pop `A`, pop `B` and free it, then push a new
+/// node that reuses the memory of `A`. The swap still succeeds since the head has the same address,
+/// but it installs the freed `B` as the new head.
+///
+/// Nodes are freed through [epoch](super::epoch) based reclamation, which solves this: a thread
+/// is pinned from the moment it loads the head until its swap, so the memory of `A` can not be
+/// reused in the meantime. A head with the same address is then the very same node, whose next
+/// node has not changed since nodes are never modified once pushed.
+#[derive(Debug)]
+pub struct TreiberStack<T> {
+    head: AtomicPtr<Node<T>>,
+}
+
+// SAFETY: Values are moved in and out of the stack by value, so sending them between threads is
+// all that sharing the stack does.
+unsafe impl<T> Send for TreiberStack<T> where T: Send {}
+
+// SAFETY: Same as above, a shared stack gives no reference to its values.
+unsafe impl<T> Sync for TreiberStack<T> where T: Send {}
+
+impl<T> TreiberStack<T> {
+    /// Create an empty stack.
+    pub const fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Add a value to the top of the stack.
+    pub fn push(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node {
+            value: ManuallyDrop::new(value),
+            next: self.head.load(Ordering::Relaxed),
+        }));
+        // Pushing never reads another node, so there is no need to be pinned.
+        loop {
+            // SAFETY: The node is not shared until the swap succeeds.
+            let next = unsafe { (*node).next };
+            // Release makes the node visible to the thread that pops it.
+            match self
+                .head
+                .compare_exchange_weak(next, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                // SAFETY: Same as above.
+                Err(head) => unsafe { (*node).next = head },
+            }
+        }
+    }
+
+    /// Remove the value at the top of the stack, returns `None` if the stack is empty.
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if head.is_null() {
+                return None;
+            }
+            // SAFETY: The head was reachable when we loaded it while pinned, so it can not have
+            // been freed. Nodes are never modified once pushed.
+            let next = unsafe { (*head).next };
+            if self
+                .head
+                .compare_exchange_weak(head, next, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                // SAFETY: We won the race for this node, so we are the only thread that takes its
+                // value.
+                let value = unsafe { ManuallyDrop::take(&mut (*head).value) };
+                // SAFETY: The node is no longer reachable from the stack, and its value has been
+                // taken, so freeing it does not drop any `T`.
+                unsafe { guard.defer_destroy(head) };
+                return Some(value);
+            }
+        }
+    }
+
+    /// Return whether the stack is empty. The answer might be outdated as soon as it is returned.
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Relaxed).is_null()
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        // We have exclusive access to the stack, so the nodes can be freed right away.
+        let mut node = *self.head.get_mut();
+        while !node.is_null() {
+            // SAFETY: Every node is owned by the stack and holds a value.
+            let mut boxed = unsafe { Box::from_raw(node) };
+            // SAFETY: The value is dropped once, before the node is freed.
+            unsafe { ManuallyDrop::drop(&mut boxed.value) };
+            node = boxed.next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atomics::Arc;
+    use std::thread;
+
+    #[test]
+    fn values_come_out_in_reverse_order() {
+        let stack = TreiberStack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        for i in 0..10 {
+            stack.push(i);
+        }
+        assert!(!stack.is_empty());
+        assert_eq!(
+            (0..10).map(|_| stack.pop().unwrap()).collect::<Vec<_>>(),
+            (0..10).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn remaining_values_are_dropped_with_the_stack() {
+        let value = Arc::new(());
+        let stack = TreiberStack::new();
+        for _ in 0..3 {
+            stack.push(value.clone());
+        }
+        drop(stack.pop());
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(stack);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    /// Threads keep popping a value and pushing it back, so nodes are constantly freed and their
+    /// memory is likely to be reused by the next push. Without reclamation this is where the ABA
+    /// problem would show up, as lost or duplicated values.
+    #[test]
+    fn concurrent_pop_and_push_back() {
+        const N_THREADS: usize = 8;
+        const N_VALUES: usize = 16;
+        const N_ROUNDS: usize = 10_000;
+
+        let stack = Arc::new(TreiberStack::new());
+        for v in 0..N_VALUES {
+            stack.push(v);
+        }
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    for _ in 0..N_ROUNDS {
+                        if let Some(v) = stack.pop() {
+                            stack.push(v);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        let mut values: Vec<_> = std::iter::from_fn(|| stack.pop()).collect();
+        values.sort_unstable();
+        assert_eq!(values, (0..N_VALUES).collect::<Vec<_>>());
+    }
+}
@@ -1,10 +1,19 @@
 //! Structure that enables mutability to a value through a shared reference.
 
+mod lazy;
+mod once;
+
+pub use lazy::LazyCell;
+pub use once::OnceCell;
+
 use std::cell::UnsafeCell;
+use std::{mem, ptr};
 
 /// Cell enable interior mutability
 #[derive(Debug)]
-pub struct Cell<T> {
+// `Cell<T>` has the same layout as `T`, which is needed by `from_mut` and `as_slice_of_cells`.
+#[repr(transparent)]
+pub struct Cell<T: ?Sized> {
     // This has to be an UnsafeCell so that it can be mutated through a shared reference.
     // `Cell<T>` is `!Sync` because it contains `UnsafeCell<T>`, which is also `!Sync`.
     value: UnsafeCell<T>,
@@ -20,10 +29,9 @@ impl<T> Cell<T> {
 
     /// Change the inner value.
     pub fn set(&self, value: T) {
-        // SAFETY: The value is not accessed concurrently by multiple threads (!Sync)
-        // SAFETY: No reference to the underlying value was given out, set does not invalidate any
-        // existing reference
-        unsafe { *self.value.get() = value };
+        // The old value is dropped after the cell has been updated, so its destructor can not
+        // observe the cell while it is being written to.
+        drop(self.replace(value));
     }
 
     /// Get the inner value.
@@ -35,6 +43,77 @@ impl<T> Cell<T> {
         // SAFETY: No reference to the inner value was given out (Copy)
         unsafe { *self.value.get() }
     }
+
+    /// Change the inner value and return the old one.
+    pub fn replace(&self, value: T) -> T {
+        // SAFETY: The value is not accessed concurrently by multiple threads (!Sync)
+        // SAFETY: No reference to the underlying value was given out, replace does not invalidate
+        // any existing reference
+        unsafe { mem::replace(&mut *self.value.get(), value) }
+    }
+
+    /// Take the inner value, leaving `Default::default()` in its place.
+    pub fn take(&self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default())
+    }
+
+    /// Swap the inner values of two cells.
+    pub fn swap(&self, other: &Cell<T>) {
+        if ptr::eq(self, other) {
+            return;
+        }
+        // SAFETY: The values are not accessed concurrently by multiple threads (!Sync)
+        // SAFETY: No reference to the underlying values was given out, `ptr::swap` also allows
+        // the two cells to overlap.
+        unsafe { ptr::swap(self.value.get(), other.value.get()) };
+    }
+
+    /// Update the inner value with the result of `f` on its current value.
+    pub fn update<F>(&self, f: F)
+    where
+        T: Copy,
+        F: FnOnce(T) -> T,
+    {
+        self.set(f(self.get()));
+    }
+
+    /// Consume the cell and return the inner value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Cell<T> {
+    /// Get a mutable reference to the inner value. The borrow checker statically ensures that
+    /// there is no other reference to the cell.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Turn a mutable reference into a reference to a cell.
+    pub fn from_mut(t: &mut T) -> &Cell<T> {
+        // SAFETY: `Cell<T>` has the same layout as `T`, and the cell borrows `t` exclusively for
+        // as long as it is alive.
+        unsafe { &*(t as *mut T as *const Cell<T>) }
+    }
+}
+
+impl<T> Cell<[T]> {
+    /// Turn a cell of a slice into a slice of cells.
+    pub fn as_slice_of_cells(&self) -> &[Cell<T>] {
+        // SAFETY: `Cell<T>` has the same layout as `T`, so `Cell<[T]>` has the same layout as
+        // `[Cell<T>]`.
+        unsafe { &*(self as *const Cell<[T]> as *const [Cell<T>]) }
+    }
+}
+
+impl<T: Default> Default for Cell<T> {
+    fn default() -> Self {
+        Cell::new(T::default())
+    }
 }
 
 #[cfg(test)]
@@ -45,4 +124,37 @@ mod tests {
     fn it_works() {
         Cell::new(0);
     }
+
+    #[test]
+    fn non_copy_values_can_be_read_back() {
+        let c = Cell::new(String::from("a"));
+        assert_eq!(c.replace(String::from("b")), "a");
+        assert_eq!(c.take(), "b");
+        assert_eq!(c.into_inner(), "");
+    }
+
+    #[test]
+    fn swap_and_update() {
+        let a = Cell::new(1);
+        let b = Cell::new(2);
+        a.swap(&b);
+        a.swap(&a);
+        assert_eq!((a.get(), b.get()), (2, 1));
+
+        a.update(|v| v * 21);
+        assert_eq!(a.get(), 42);
+
+        let mut b = b;
+        *b.get_mut() += 1;
+        assert_eq!(b.get(), 2);
+    }
+
+    #[test]
+    fn slice_of_cells() {
+        let mut v = [1, 2, 3];
+        let cells = Cell::from_mut(&mut v[..]).as_slice_of_cells();
+        cells[0].swap(&cells[2]);
+        cells[1].set(cells[0].get() + cells[2].get());
+        assert_eq!(v, [3, 4, 1]);
+    }
 }
@@ -0,0 +1,162 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+
+/// The state of a `LazyCell`.
+enum State<T, F> {
+    Uninit(F),
+    /// The initializer is running.
+    Running,
+    Init(T),
+    /// The initializer panicked.
+    Poisoned,
+}
+
+/// A value that is initialized on its first access.
+///
+/// The initializer runs at most once. Accessing the value from within its own initializer panics,
+/// as does accessing it after the initializer has panicked.
+pub struct LazyCell<T, F = fn() -> T> {
+    // `LazyCell<T, F>` is `!Sync` because it contains `UnsafeCell`, which is also `!Sync`.
+    state: UnsafeCell<State<T, F>>,
+}
+
+impl<T, F: FnOnce() -> T> LazyCell<T, F> {
+    /// Create a cell that is initialized by `f` on its first access.
+    pub const fn new(f: F) -> Self {
+        Self {
+            state: UnsafeCell::new(State::Uninit(f)),
+        }
+    }
+
+    /// Force the initialization and get a reference to the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initializer panics, or if it accesses the cell itself.
+    pub fn force(this: &LazyCell<T, F>) -> &T {
+        // SAFETY: The state is not accessed concurrently by multiple threads (!Sync), and no
+        // mutable reference to it is alive outside of the initialization below.
+        if let State::Uninit(_) = unsafe { &*this.state.get() } {
+            // SAFETY: References to the value are only given out once it is initialized, so no
+            // reference to the state is alive.
+            let state = unsafe { &mut *this.state.get() };
+            let State::Uninit(f) = mem::replace(state, State::Running) else {
+                unreachable!();
+            };
+            // Poison the cell if the initializer panics.
+            let guard = PoisonOnPanic(this);
+            // The initializer might access the cell, so `state` must not be used after the call.
+            let value = f();
+            mem::forget(guard);
+            // SAFETY: Accesses from the initializer panicked when they saw `State::Running`, so no
+            // reference to the state is alive.
+            unsafe { *this.state.get() = State::Init(value) };
+        }
+        // SAFETY: The state is only written to when it is not initialized.
+        match unsafe { &*this.state.get() } {
+            State::Init(value) => value,
+            State::Running => panic!("LazyCell accessed from within its initializer"),
+            State::Poisoned => panic!("LazyCell instance has previously been poisoned"),
+            State::Uninit(_) => unreachable!(),
+        }
+    }
+
+    /// Consume the cell and return the value, or the initializer if it has not run yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell has been poisoned.
+    pub fn into_inner(this: Self) -> Result<T, F> {
+        match this.state.into_inner() {
+            State::Init(value) => Ok(value),
+            State::Uninit(f) => Err(f),
+            State::Running | State::Poisoned => {
+                panic!("LazyCell instance has previously been poisoned")
+            }
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for LazyCell<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        LazyCell::force(self)
+    }
+}
+
+impl<T: Default> Default for LazyCell<T> {
+    fn default() -> Self {
+        LazyCell::new(T::default)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for LazyCell<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // SAFETY: The state is not accessed concurrently by multiple threads (!Sync), and it can
+        // not be written to by the initializer while we are looking at it.
+        match unsafe { &*self.state.get() } {
+            State::Init(value) => f.debug_tuple("LazyCell").field(value).finish(),
+            _ => f.write_str("LazyCell(<uninit>)"),
+        }
+    }
+}
+
+/// Marks the cell as poisoned when dropped, which only happens if the initializer panics.
+struct PoisonOnPanic<'a, T, F>(&'a LazyCell<T, F>);
+
+impl<T, F> Drop for PoisonOnPanic<'_, T, F> {
+    fn drop(&mut self) {
+        // SAFETY: The initializer has unwound, so no reference to the state is alive.
+        unsafe { *self.0.state.get() = State::Poisoned };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::{Cell, OnceCell};
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn initializer_runs_once() {
+        let calls = Cell::new(0);
+        let lazy = LazyCell::new(|| {
+            calls.set(calls.get() + 1);
+            42
+        });
+        assert_eq!(calls.get(), 0);
+        assert_eq!(*lazy, 42);
+        assert_eq!(*LazyCell::force(&lazy), 42);
+        assert_eq!(calls.get(), 1);
+        assert_eq!(LazyCell::into_inner(lazy).ok(), Some(42));
+    }
+
+    #[test]
+    #[should_panic(expected = "LazyCell accessed from within its initializer")]
+    fn reentrant_access_panics() {
+        type Lazy = LazyCell<i32, Box<dyn FnOnce() -> i32>>;
+
+        // Both are leaked so that the initializer can refer to the cell it initializes.
+        let this: &'static OnceCell<&'static Lazy> = Box::leak(Box::new(OnceCell::new()));
+        let init: Box<dyn FnOnce() -> i32> = Box::new(move || ***this.get().unwrap() + 1);
+        let lazy: &'static Lazy = Box::leak(Box::new(LazyCell::new(init)));
+        let _ = this.set(lazy);
+        let _ = **lazy;
+    }
+
+    #[test]
+    fn panicking_initializer_poisons_the_cell() {
+        let lazy = LazyCell::new(|| -> i32 { panic!("init failed") });
+        let res = panic::catch_unwind(AssertUnwindSafe(|| *lazy));
+        assert!(res.is_err());
+        let res = panic::catch_unwind(AssertUnwindSafe(|| *lazy));
+        let msg = res.unwrap_err();
+        assert_eq!(
+            msg.downcast_ref::<&str>(),
+            Some(&"LazyCell instance has previously been poisoned")
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+
+/// A cell that can be written to only once.
+///
+/// Unlike [`RefCell`](crate::refcell::RefCell), a shared reference to the value can be obtained
+/// without a guard, since the value is never changed after it has been set.
+pub struct OnceCell<T> {
+    // `OnceCell<T>` is `!Sync` because it contains `UnsafeCell`, which is also `!Sync`.
+    value: UnsafeCell<Option<T>>,
+}
+
+impl<T> OnceCell<T> {
+    /// Create an empty cell.
+    pub const fn new() -> Self {
+        Self {
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Get a reference to the value, returns `None` if the cell is empty.
+    pub fn get(&self) -> Option<&T> {
+        // SAFETY: The value is not accessed concurrently by multiple threads (!Sync)
+        // SAFETY: No mutable reference to the value is given out through a shared reference to
+        // the cell, the value is only written to while the cell is empty.
+        unsafe { (*self.value.get()).as_ref() }
+    }
+
+    /// Get a mutable reference to the value, returns `None` if the cell is empty.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut().as_mut()
+    }
+
+    /// Set the value of the cell. The given value is returned back if the cell is not empty.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self.get().is_some() {
+            return Err(value);
+        }
+        // SAFETY: The value is not accessed concurrently by multiple threads (!Sync)
+        // SAFETY: The cell is empty, so no reference to the value was given out.
+        unsafe { *self.value.get() = Some(value) };
+        Ok(())
+    }
+
+    /// Get a reference to the value, initializing the cell with `f` if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` panics, the cell is left empty in that case. It also panics if `f`
+    /// initializes the cell itself.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        match self.get_or_try_init(|| Ok::<T, std::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Get a reference to the value, initializing the cell with `f` if it is empty. The cell is
+    /// left empty if `f` returns an error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` panics, the cell is left empty in that case. It also panics if `f`
+    /// initializes the cell itself.
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        let value = f()?;
+        // `f` can reach the cell through a shared reference. Overwriting a value it has set would
+        // invalidate the references that it might have handed out.
+        assert!(self.set(value).is_ok(), "reentrant init");
+        Ok(self.get().expect("The cell has just been set"))
+    }
+
+    /// Take the value out of the cell, leaving it empty.
+    pub fn take(&mut self) -> Option<T> {
+        self.value.get_mut().take()
+    }
+
+    /// Consume the cell and return its value.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceCell").field(value).finish(),
+            None => f.write_str("OnceCell(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_only_once() {
+        let mut cell = OnceCell::new();
+        assert!(cell.get().is_none());
+        assert!(cell.set(1).is_ok());
+        assert_eq!(cell.set(2), Err(2));
+        assert_eq!(cell.get(), Some(&1));
+
+        assert_eq!(cell.take(), Some(1));
+        assert!(cell.get().is_none());
+        assert_eq!(*cell.get_or_init(|| 3), 3);
+        assert_eq!(cell.into_inner(), Some(3));
+    }
+
+    #[test]
+    fn failed_init_leaves_cell_empty() {
+        let cell = OnceCell::new();
+        assert_eq!(cell.get_or_try_init(|| Err(())), Err(()));
+        assert!(cell.get().is_none());
+        assert_eq!(cell.get_or_try_init(|| Ok::<_, ()>(1)), Ok(&1));
+        assert_eq!(cell.get_or_try_init(|| Err(())), Ok(&1));
+    }
+
+    #[test]
+    #[should_panic(expected = "reentrant init")]
+    fn reentrant_init_panics() {
+        let cell = OnceCell::new();
+        cell.get_or_init(|| {
+            let inner = *cell.get_or_init(|| 1);
+            inner + 1
+        });
+    }
+}
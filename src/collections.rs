@@ -1,7 +1,9 @@
 //! Data structures for containing multiple items.
 
-mod doubly_linked_list;
+pub mod doubly_linked_list;
 mod linked_hash_map;
+pub mod slab_list;
 
-pub use doubly_linked_list::DoublyLinkedList;
+pub use doubly_linked_list::{Cursor, CursorMut, DoublyLinkedList};
 pub use linked_hash_map::LinkedHashMap;
+pub use slab_list::SlabList;
@@ -1,4 +1,16 @@
-use std::{marker::PhantomData, ptr::NonNull};
+//! A doubly-linked list with owned nodes, along with its iterators and cursors.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    marker::PhantomData,
+    ptr::NonNull,
+};
+
+/// A possibly missing pointer to a node, `None` stands for an end of the list.
+type Link<T> = Option<NonNull<Node<T>>>;
 
 #[derive(Debug)]
 struct Node<T> {
@@ -25,7 +37,64 @@ impl<T> Node<T> {
 /// NOTE: It is almost always better to use Vec or VecDeque because array-based
 /// containers are generally faster, more memory efficient, and make better use
 /// of CPU cache.
-#[derive(Debug)]
+///
+/// # Variance
+///
+/// The list owns its elements, so it is covariant in `T` just like `Box<T>`
+/// or `Vec<T>`. A list of longer-lived references can be used where a list of
+/// shorter-lived ones is expected, the same goes for its immutable and owning
+/// iterators.
+///
+/// ```
+/// use rusty_crust::collections::doubly_linked_list::{IntoIter, Iter};
+/// use rusty_crust::collections::DoublyLinkedList;
+///
+/// fn shorten<'a>(list: DoublyLinkedList<&'static str>) -> DoublyLinkedList<&'a str> {
+///     list
+/// }
+///
+/// fn shorten_iter<'i, 'a>(iter: Iter<'i, &'static str>) -> Iter<'i, &'a str> {
+///     iter
+/// }
+///
+/// fn shorten_into_iter<'a>(iter: IntoIter<&'static str>) -> IntoIter<&'a str> {
+///     iter
+/// }
+/// ```
+///
+/// The variance only goes one way, a list of short-lived references can not
+/// pretend to hold longer-lived ones.
+///
+/// ```compile_fail
+/// use rusty_crust::collections::DoublyLinkedList;
+///
+/// fn lengthen<'a>(list: DoublyLinkedList<&'a str>) -> DoublyLinkedList<&'static str> {
+///     list
+/// }
+/// ```
+///
+/// A mutable iterator is invariant in `T`, otherwise it would be possible to
+/// write short-lived references into a list of longer-lived ones.
+///
+/// ```compile_fail
+/// use rusty_crust::collections::doubly_linked_list::IterMut;
+///
+/// fn shorten_iter_mut<'i, 'a>(iter: IterMut<'i, &'static str>) -> IterMut<'i, &'a str> {
+///     iter
+/// }
+/// ```
+///
+/// And it can not outlive the list that it borrows.
+///
+/// ```compile_fail
+/// use rusty_crust::collections::DoublyLinkedList;
+///
+/// let mut iter = {
+///     let mut list = DoublyLinkedList::from([1, 2, 3]);
+///     list.iter_mut()
+/// };
+/// *iter.next().unwrap() += 1;
+/// ```
 pub struct DoublyLinkedList<T> {
     head: Option<NonNull<Node<T>>>,
     tail: Option<NonNull<Node<T>>>,
@@ -33,6 +102,16 @@ pub struct DoublyLinkedList<T> {
     marker: PhantomData<Box<Node<T>>>,
 }
 
+// SAFETY: The list owns its nodes, so moving it to another thread moves the
+// elements along, which is fine when they are Send.
+#[allow(unsafe_code)]
+unsafe impl<T: Send> Send for DoublyLinkedList<T> {}
+
+// SAFETY: A shared list only gives out shared references to its elements, which
+// is fine when they are Sync.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for DoublyLinkedList<T> {}
+
 impl<T> Default for DoublyLinkedList<T> {
     fn default() -> Self {
         Self {
@@ -50,6 +129,92 @@ impl<T> Drop for DoublyLinkedList<T> {
     }
 }
 
+impl<T: Clone> Clone for DoublyLinkedList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+
+    /// Reuses the nodes of the list, only allocating or deallocating nodes for
+    /// the difference in length.
+    fn clone_from(&mut self, source: &Self) {
+        if self.len > source.len {
+            self.split_off(source.len);
+        }
+        let mut source_iter = source.iter();
+        for (data, source_data) in self.iter_mut().zip(&mut source_iter) {
+            data.clone_from(source_data);
+        }
+        self.extend(source_iter.cloned());
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for DoublyLinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T> FromIterator<T> for DoublyLinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for DoublyLinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|data| self.push_back(data));
+    }
+}
+
+impl<'a, T: 'a + Copy> Extend<&'a T> for DoublyLinkedList<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for DoublyLinkedList<T> {
+    fn from(array: [T; N]) -> Self {
+        Self::from_iter(array)
+    }
+}
+
+impl<T> From<Vec<T>> for DoublyLinkedList<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_iter(vec)
+    }
+}
+
+impl<T: PartialEq> PartialEq for DoublyLinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for DoublyLinkedList<T> {}
+
+impl<T: PartialOrd> PartialOrd for DoublyLinkedList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other)
+    }
+}
+
+impl<T: Ord> Ord for DoublyLinkedList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other)
+    }
+}
+
+impl<T: Hash> Hash for DoublyLinkedList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The length is hashed first, so that the elements of a list can not
+        // be split up differently into a list of lists with the same hash.
+        self.len.hash(state);
+        self.iter().for_each(|data| data.hash(state));
+    }
+}
+
 impl<T> DoublyLinkedList<T> {
     /// Creates an empty DoublyLinkedList.
     ///
@@ -102,20 +267,21 @@ impl<T> DoublyLinkedList<T> {
     pub fn append(&mut self, other: &mut DoublyLinkedList<T>) {
         match self.tail {
             Some(mut tail) => {
-                // SAFETY: Our tail is Some so we know that the pointer is still
-                // valid and we can direference the raw pointer to access its
-                // data
-                unsafe { tail.as_mut().next = other.head };
                 if let Some(mut head) = other.head {
+                    // SAFETY: Our tail is Some so we know that the pointer is
+                    // still valid and we can direference the raw pointer to
+                    // access its data
+                    unsafe { tail.as_mut().next = Some(head) };
                     // SAFETY: Other's head is Some so we know that the pointer
                     // is still valid and we can dereference the raw pointer
                     // to access its data
-                    unsafe { head.as_mut().prev = self.tail };
+                    unsafe { head.as_mut().prev = Some(tail) };
+                    self.tail = other.tail;
                 }
             }
             None => {
                 self.head = other.head;
-                self.tail = other.head;
+                self.tail = other.tail;
             }
         }
         self.len += other.len;
@@ -124,7 +290,7 @@ impl<T> DoublyLinkedList<T> {
         other.len = 0;
     }
 
-    /// Provides a forward iterator.
+    /// Provides an iterator that can be walked from both ends.
     ///
     /// # Examples
     ///
@@ -142,15 +308,23 @@ impl<T> DoublyLinkedList<T> {
     /// assert_eq!(iter.next(), Some(&1));
     /// assert_eq!(iter.next(), Some(&2));
     /// assert_eq!(iter.next(), None);
+    ///
+    /// let mut iter = list.iter().rev();
+    /// assert_eq!(iter.len(), 3);
+    /// assert_eq!(iter.next(), Some(&2));
+    /// assert_eq!(iter.len(), 2);
     /// ```
     pub fn iter(&self) -> Iter<'_, T> {
         Iter {
-            it: self.head,
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
             marker: PhantomData,
         }
     }
 
-    /// Provides a forward iterator with mutable references.
+    /// Provides an iterator with mutable references that can be walked from
+    /// both ends.
     ///
     /// # Examples
     ///
@@ -175,11 +349,129 @@ impl<T> DoublyLinkedList<T> {
     /// ```
     pub fn iter_mut(&mut self) -> IterMut<'_, T> {
         IterMut {
-            it: self.head,
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
             marker: PhantomData,
         }
     }
 
+    /// Provides a cursor at the front element.
+    ///
+    /// The cursor is pointing to the "ghost" non-element if the list is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::new();
+    /// list.push_back(1);
+    /// list.push_back(2);
+    ///
+    /// let mut cursor = list.cursor_front();
+    /// assert_eq!(cursor.current(), Some(&1));
+    /// assert_eq!(cursor.peek_next(), Some(&2));
+    ///
+    /// cursor.move_next();
+    /// cursor.move_next();
+    /// assert_eq!(cursor.current(), None);
+    /// assert_eq!(cursor.index(), None);
+    /// ```
+    pub fn cursor_front(&self) -> Cursor<'_, T> {
+        Cursor {
+            index: 0,
+            current: self.head,
+            list: self,
+        }
+    }
+
+    /// Provides a cursor with editing operations at the front element.
+    ///
+    /// The cursor is pointing to the "ghost" non-element if the list is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::new();
+    /// list.push_back(1);
+    /// list.push_back(3);
+    ///
+    /// let mut cursor = list.cursor_front_mut();
+    /// cursor.insert_after(2);
+    /// cursor.move_next();
+    /// assert_eq!(cursor.remove_current(), Some(2));
+    /// assert_eq!(cursor.current(), Some(&mut 3));
+    ///
+    /// let mut iter = list.iter();
+    /// assert_eq!(iter.next(), Some(&1));
+    /// assert_eq!(iter.next(), Some(&3));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            index: 0,
+            current: self.head,
+            list: self,
+        }
+    }
+
+    /// Provides a cursor at the back element.
+    ///
+    /// The cursor is pointing to the "ghost" non-element if the list is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::new();
+    /// list.push_back(1);
+    /// list.push_back(2);
+    ///
+    /// let mut cursor = list.cursor_back();
+    /// assert_eq!(cursor.current(), Some(&2));
+    /// assert_eq!(cursor.index(), Some(1));
+    ///
+    /// cursor.move_prev();
+    /// assert_eq!(cursor.current(), Some(&1));
+    /// ```
+    pub fn cursor_back(&self) -> Cursor<'_, T> {
+        Cursor {
+            index: self.len.saturating_sub(1),
+            current: self.tail,
+            list: self,
+        }
+    }
+
+    /// Provides a cursor with editing operations at the back element.
+    ///
+    /// The cursor is pointing to the "ghost" non-element if the list is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::new();
+    /// list.push_back(1);
+    /// list.push_back(2);
+    ///
+    /// let mut cursor = list.cursor_back_mut();
+    /// let front = cursor.split_before();
+    /// assert_eq!(front.front(), Some(&1));
+    /// assert_eq!(list.front(), Some(&2));
+    /// ```
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            index: self.len.saturating_sub(1),
+            current: self.tail,
+            list: self,
+        }
+    }
+
     /// Returns true if the DoublyLinkedList is empty.
     ///
     /// This operation should compute in O(1) time.
@@ -549,7 +841,7 @@ impl<T> DoublyLinkedList<T> {
     /// Splits the list into two at the given index. Returns everything after
     /// the given index, including the index.
     ///
-    /// This operation should compute in O(n) time.
+    /// This operation should compute in O(min(at, len - at)) time.
     ///
     /// # Panics
     ///
@@ -582,144 +874,1493 @@ impl<T> DoublyLinkedList<T> {
             return Self::new();
         }
 
-        let mut it_idx = 0;
-        let mut it_node = self.head;
-        loop {
-            match it_node {
-                None => unreachable!(),
-                Some(mut node_ptr) => {
-                    let node = unsafe { node_ptr.as_mut() };
-
-                    if it_idx == at {
-                        if let Some(mut prev) = node.prev {
-                            unsafe { prev.as_mut().next = None };
-                        }
-                        let ll = DoublyLinkedList {
-                            head: Some(unsafe { NonNull::new_unchecked(node) }),
-                            tail: self.tail,
-                            len: len - at,
-                            marker: PhantomData,
-                        };
-                        self.tail = node.prev;
-                        node.prev = None;
-                        break ll;
-                    }
-
-                    it_idx += 1;
-                    it_node = node.next;
-                }
-            }
-        }
+        // SAFETY: The node was found by walking the list so it belongs to the
+        // list, and `at` elements come before it
+        let front = unsafe { self.split_off_before_node(self.node_at(at), at) };
+        std::mem::replace(self, front)
     }
-}
-
-/// An iterator over the elements of a DoublyLinkedList.
-///
-/// This struct is created by [`DoublyLinkedList::iter()`]. See its
-/// documentation for more.
-///
-/// [`DoublyLinkedList::iter()`]: crate::collections::DoublyLinkedList#iter;
-#[derive(Debug)]
-pub struct Iter<'a, T> {
-    it: Option<NonNull<Node<T>>>,
-    marker: PhantomData<&'a Node<T>>,
-}
-
-impl<'a, T> Iterator for Iter<'a, T> {
-    type Item = &'a T;
 
+    /// Provides a reference to the element at the given index, or None if the
+    /// index is out of bounds.
+    ///
+    /// This operation should compute in O(min(at, len - at)) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let list = DoublyLinkedList::from([1, 2, 3]);
+    /// assert_eq!(list.get(1), Some(&2));
+    /// assert_eq!(list.get(3), None);
+    /// ```
     #[allow(unsafe_code)]
-    fn next(&mut self) -> Option<Self::Item> {
-        // SAFETY: Current Node is Some, so we know its raw pointer is
+    pub fn get(&self, at: usize) -> Option<&T> {
+        // SAFETY: The node was found by walking the list so its raw pointer is
         // still valid
-        self.it.map(|node| unsafe {
-            let node = &mut *node.as_ptr();
-            self.it = node.next;
-            &node.data
-        })
+        self.node_at(at)
+            .map(|node| unsafe { &(*node.as_ptr()).data })
     }
-}
-
-/// A mutable iterator over the elements of a DoublyLinkedList.
-///
-/// This struct is created by [`DoublyLinkedList::iter_mut()`]. See its
-/// documentation for more.
-///
-/// [`DoublyLinkedList::iter_mut()`]: crate::collections::DoublyLinkedList#iter_mut;
-#[derive(Debug)]
-pub struct IterMut<'a, T> {
-    it: Option<NonNull<Node<T>>>,
-    marker: PhantomData<&'a mut Node<T>>,
-}
-
-impl<'a, T> Iterator for IterMut<'a, T> {
-    type Item = &'a mut T;
 
+    /// Provides a mutable reference to the element at the given index, or
+    /// None if the index is out of bounds.
+    ///
+    /// This operation should compute in O(min(at, len - at)) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 3]);
+    /// if let Some(data) = list.get_mut(1) {
+    ///     *data = 5;
+    /// }
+    /// assert_eq!(list, DoublyLinkedList::from([1, 5, 3]));
+    /// ```
     #[allow(unsafe_code)]
-    fn next(&mut self) -> Option<Self::Item> {
-        // SAFETY: Current Node is Some, so we know its raw pointer is still
-        // valid
-        self.it.map(|node| unsafe {
-            let node = &mut *node.as_ptr();
-            self.it = node.next;
-            &mut node.data
-        })
+    pub fn get_mut(&mut self, at: usize) -> Option<&mut T> {
+        // SAFETY: The node was found by walking the list so its raw pointer is
+        // still valid, and the list is borrowed mutably
+        self.node_at(at)
+            .map(|node| unsafe { &mut (*node.as_ptr()).data })
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn push_front_pop_front() {
-        let mut ll = DoublyLinkedList::new();
-        assert!(ll.is_empty());
-        assert_eq!(ll.len(), 0);
-
-        ll.push_front(0);
-        ll.push_front(1);
-        ll.push_front(2);
-
-        assert!(!ll.is_empty());
-        assert_eq!(ll.len(), 3);
 
-        assert_eq!(ll.pop_front(), Some(2));
-        assert_eq!(ll.pop_front(), Some(1));
-        assert_eq!(ll.pop_front(), Some(0));
-        assert_eq!(ll.pop_front(), None);
+    /// Inserts an element at the given index, shifting all elements after it
+    /// towards the back.
+    ///
+    /// This operation should compute in O(min(at, len - at)) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from(['a', 'c']);
+    /// list.insert(1, 'b');
+    /// list.insert(3, 'd');
+    /// assert_eq!(list, DoublyLinkedList::from(['a', 'b', 'c', 'd']));
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn insert(&mut self, at: usize, data: T) {
+        assert!(at <= self.len, "Cannot insert at a nonexistent index");
 
-        assert!(ll.is_empty());
-        assert_eq!(ll.len(), 0);
+        let node = NonNull::from(Box::leak(Box::new(Node::new(data))));
+        let next = self.node_at(at);
+        let prev = match next {
+            None => self.tail,
+            // SAFETY: The node was found by walking the list so its raw
+            // pointer is still valid
+            Some(next) => unsafe { next.as_ref().prev },
+        };
+        // SAFETY: The neighbours are adjacent nodes of the list, and the new
+        // node has just been allocated
+        unsafe { self.splice_nodes(prev, next, node, node, 1) };
     }
 
-    #[test]
-    fn push_front_pop_back() {
-        let mut ll = DoublyLinkedList::new();
-        assert!(ll.is_empty());
-        assert_eq!(ll.len(), 0);
-
-        ll.push_front(0);
-        ll.push_front(1);
-        ll.push_front(2);
-
-        assert!(!ll.is_empty());
-        assert_eq!(ll.len(), 3);
-
-        assert_eq!(ll.pop_back(), Some(0));
-        assert_eq!(ll.pop_back(), Some(1));
-        assert_eq!(ll.pop_back(), Some(2));
-        assert_eq!(ll.pop_back(), None);
-
-        assert!(ll.is_empty());
-        assert_eq!(ll.len(), 0);
+    /// Removes the element at the given index and returns it, or None if the
+    /// index is out of bounds.
+    ///
+    /// This operation should compute in O(min(at, len - at)) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 3]);
+    /// assert_eq!(list.remove(1), Some(2));
+    /// assert_eq!(list.remove(2), None);
+    /// assert_eq!(list, DoublyLinkedList::from([1, 3]));
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn remove(&mut self, at: usize) -> Option<T> {
+        let node = self.node_at(at)?;
+        // SAFETY: The node was found by walking the list so it belongs to the
+        // list. Once unlinked, nothing points to the node anymore so we can
+        // take back its ownership.
+        unsafe {
+            self.unlink_node(node);
+            Some(Box::from_raw(node.as_ptr()).data)
+        }
     }
 
-    #[test]
-    fn push_back_pop_back() {
-        let mut ll = DoublyLinkedList::new();
-        assert!(ll.is_empty());
-        assert_eq!(ll.len(), 0);
+    /// Swaps the elements at the given indices.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either index is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 3]);
+    /// list.swap(0, 2);
+    /// assert_eq!(list, DoublyLinkedList::from([3, 2, 1]));
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn swap(&mut self, i: usize, j: usize) {
+        let len = self.len;
+        assert!(i < len && j < len, "Cannot swap at a nonexistent index");
+        if i == j {
+            return;
+        }
+        if let (Some(a), Some(b)) = (self.node_at(i), self.node_at(j)) {
+            // SAFETY: The nodes were found by walking the list so their raw
+            // pointers are still valid, and they are distinct since `i != j`
+            unsafe { std::mem::swap(&mut (*a.as_ptr()).data, &mut (*b.as_ptr()).data) };
+        }
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, removes all elements `e` for which `f(&e)` returns
+    /// false. This method operates in place, visiting each element exactly
+    /// once in the original order, and preserves the order of the retained
+    /// elements.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 3, 4]);
+    /// list.retain(|&x| x % 2 == 0);
+    /// assert_eq!(list, DoublyLinkedList::from([2, 4]));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|data| f(data));
+    }
+
+    /// Retains only the elements specified by the predicate, which can mutate
+    /// the elements.
+    ///
+    /// In other words, removes all elements `e` for which `f(&mut e)` returns
+    /// false. This method operates in place, visiting each element exactly
+    /// once in the original order, and preserves the order of the retained
+    /// elements.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 3, 4]);
+    /// list.retain_mut(|x| {
+    ///     *x += 1;
+    ///     *x % 2 == 0
+    /// });
+    /// assert_eq!(list, DoublyLinkedList::from([2, 4]));
+    /// ```
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        self.extract_if(|data| !f(data)).for_each(drop);
+    }
+
+    /// Creates an iterator which uses a closure to determine if an element
+    /// should be removed.
+    ///
+    /// If the closure returns true, then the element is removed and yielded.
+    /// If the closure returns false, the element will remain in the list and
+    /// will not be yielded by the iterator.
+    ///
+    /// If the returned iterator is not exhausted, e.g. because it is dropped
+    /// without iterating or the iteration short-circuits, then the remaining
+    /// elements will be retained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut numbers = DoublyLinkedList::from([1, 2, 3, 4, 5, 6, 8, 9, 11, 13, 14, 15]);
+    ///
+    /// let evens = numbers.extract_if(|x| *x % 2 == 0).collect::<DoublyLinkedList<_>>();
+    /// let odds = numbers;
+    ///
+    /// assert_eq!(evens, DoublyLinkedList::from([2, 4, 6, 8, 14]));
+    /// assert_eq!(odds, DoublyLinkedList::from([1, 3, 5, 9, 11, 13, 15]));
+    /// ```
+    pub fn extract_if<F>(&mut self, filter: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        ExtractIf {
+            cursor: self.cursor_front_mut(),
+            filter,
+        }
+    }
+
+    /// Reverses the order of the elements in place.
+    ///
+    /// This operation should compute in O(n) time and O(1) memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 3]);
+    /// list.reverse();
+    /// assert_eq!(list, DoublyLinkedList::from([3, 2, 1]));
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn reverse(&mut self) {
+        let mut it_node = self.head;
+        while let Some(mut node) = it_node {
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            let node = unsafe { node.as_mut() };
+            std::mem::swap(&mut node.prev, &mut node.next);
+            it_node = node.prev;
+        }
+        std::mem::swap(&mut self.head, &mut self.tail);
+    }
+
+    /// Sorts the list.
+    ///
+    /// This sort is stable (i.e., does not reorder equal elements) and
+    /// relinks the existing nodes, so no element is moved or reallocated.
+    ///
+    /// This operation should compute in O(n * log(n)) time and O(1) memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([-5, 4, 1, -3, 2]);
+    /// list.sort();
+    /// assert_eq!(list, DoublyLinkedList::from([-5, -3, 1, 2, 4]));
+    /// ```
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(T::cmp);
+    }
+
+    /// Sorts the list with a comparator function.
+    ///
+    /// This sort is stable (i.e., does not reorder equal elements) and
+    /// relinks the existing nodes, so no element is moved or reallocated.
+    ///
+    /// This operation should compute in O(n * log(n)) time and O(1) memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([5, 4, 1, 3, 2]);
+    /// list.sort_by(|a, b| b.cmp(a));
+    /// assert_eq!(list, DoublyLinkedList::from([5, 4, 3, 2, 1]));
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        // Bottom-up merge sort, where `runs[i]` holds either nothing or a
+        // sorted run of 2^i nodes. Every node is merged into the runs like a
+        // carry propagating through a binary counter, so a run always holds
+        // nodes that came before the ones that are merged into it.
+        let mut chains = Chains::new(self);
+        while let Some(mut node) = chains.unsorted {
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            chains.unsorted = unsafe { node.as_mut().next.take() };
+            chains.merged = Some(node);
+            let mut rank = 0;
+            while let Some(run) = chains.runs[rank].take() {
+                chains.a = Some(run);
+                chains.b = chains.merged.take();
+                // SAFETY: All chains hold distinct nodes from the list
+                unsafe { chains.merge(&mut compare) };
+                rank += 1;
+            }
+            chains.runs[rank] = chains.merged.take();
+        }
+        for rank in 0..chains.runs.len() {
+            chains.a = chains.runs[rank].take();
+            chains.b = chains.merged.take();
+            // SAFETY: All chains hold distinct nodes from the list
+            unsafe { chains.merge(&mut compare) };
+        }
+    }
+
+    /// Sorts the list with a key extraction function.
+    ///
+    /// This sort is stable (i.e., does not reorder equal elements) and
+    /// relinks the existing nodes, so no element is moved or reallocated.
+    ///
+    /// This operation should compute in O(m * n * log(n)) time and O(1)
+    /// memory, where the key function is O(m).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([-5i32, 4, 1, -3, 2]);
+    /// list.sort_by_key(|k| k.abs());
+    /// assert_eq!(list, DoublyLinkedList::from([1, 2, -3, 4, -5]));
+    /// ```
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Moves all elements from other into the list, assuming that both lists
+    /// are sorted. The result is sorted, and elements of the list come before
+    /// equal elements of other. After this operation, other becomes empty.
+    ///
+    /// This operation should compute in O(n + m) time and O(1) memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 3, 5]);
+    /// let mut other = DoublyLinkedList::from([2, 3, 6]);
+    /// list.merge(&mut other);
+    /// assert_eq!(list, DoublyLinkedList::from([1, 2, 3, 3, 5, 6]));
+    /// assert!(other.is_empty());
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn merge(&mut self, other: &mut DoublyLinkedList<T>)
+    where
+        T: Ord,
+    {
+        let mut other = std::mem::take(other);
+        other.tail = None;
+        self.len += std::mem::take(&mut other.len);
+        let mut chains = Chains::new(self);
+        chains.a = chains.unsorted.take();
+        chains.b = other.head.take();
+        // SAFETY: Both chains hold distinct nodes, which now belong to the list
+        unsafe { chains.merge(&mut T::cmp) };
+    }
+
+    /// Removes consecutive repeated elements.
+    ///
+    /// If the list is sorted, this removes all duplicates.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([1, 2, 2, 3, 2]);
+    /// list.dedup();
+    /// assert_eq!(list, DoublyLinkedList::from([1, 2, 3, 2]));
+    /// ```
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    /// Removes all but the first of consecutive elements that resolve to the
+    /// same key.
+    ///
+    /// If the list is sorted by the key, this removes all duplicates.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from([10, 20, 21, 30, 20]);
+    /// list.dedup_by_key(|i| *i / 10);
+    /// assert_eq!(list, DoublyLinkedList::from([10, 20, 30, 20]));
+    /// ```
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        F: FnMut(&mut T) -> K,
+        K: PartialEq,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes all but the first of consecutive elements that satisfy a given
+    /// equality relation.
+    ///
+    /// The `same_bucket` function is passed references to two elements, the
+    /// first one comes after the second one in the list, and it is removed if
+    /// the function returns true.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::from(["foo", "bar", "Bar", "baz", "bar"]);
+    /// list.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    /// assert_eq!(list, DoublyLinkedList::from(["foo", "bar", "baz", "bar"]));
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let Some(mut kept) = self.head else {
+            return;
+        };
+        // SAFETY: Both nodes belong to the list, so their raw pointers are
+        // still valid. They are distinct, so the mutable references do not
+        // alias.
+        while let Some(next) = unsafe { kept.as_ref().next } {
+            let (next_data, kept_data) =
+                unsafe { (&mut (*next.as_ptr()).data, &mut (*kept.as_ptr()).data) };
+            if same_bucket(next_data, kept_data) {
+                // SAFETY: Once unlinked, nothing points to the node anymore so
+                // we can take back its ownership.
+                unsafe {
+                    self.unlink_node(next);
+                    drop(Box::from_raw(next.as_ptr()));
+                }
+            } else {
+                kept = next;
+            }
+        }
+    }
+
+    /// Returns the node at the given index, or None if the index is out of
+    /// bounds. The list is walked from whichever end is the closest.
+    #[allow(unsafe_code)]
+    fn node_at(&self, at: usize) -> Link<T> {
+        if at >= self.len {
+            return None;
+        }
+        if at < self.len / 2 {
+            let mut it_node = self.head;
+            for _ in 0..at {
+                // SAFETY: `at < len` so we never walk past the tail, every
+                // node that we visit is still valid
+                it_node = it_node.and_then(|node| unsafe { node.as_ref().next });
+            }
+            it_node
+        } else {
+            let mut it_node = self.tail;
+            for _ in at + 1..self.len {
+                // SAFETY: `at < len` so we never walk past the head, every
+                // node that we visit is still valid
+                it_node = it_node.and_then(|node| unsafe { node.as_ref().prev });
+            }
+            it_node
+        }
+    }
+
+    /// Unlinks the given node from the list without deallocating it.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node of this list.
+    #[allow(unsafe_code)]
+    unsafe fn unlink_node(&mut self, mut node: NonNull<Node<T>>) {
+        // SAFETY: The caller guarantees that the node belongs to the list, so
+        // it and its neighbours are still valid
+        let node = unsafe { node.as_mut() };
+        match node.prev {
+            Some(mut prev) => unsafe { prev.as_mut().next = node.next },
+            None => self.head = node.next,
+        }
+        match node.next {
+            Some(mut next) => unsafe { next.as_mut().prev = node.prev },
+            None => self.tail = node.prev,
+        }
+        node.prev = None;
+        node.next = None;
+        self.len -= 1;
+    }
+
+    /// Links the chain of `len` nodes going from `first` to `last` in between
+    /// `prev` and `next`. A `None` neighbour stands for the corresponding end
+    /// of the list.
+    ///
+    /// # Safety
+    ///
+    /// `prev` and `next` must be adjacent nodes of this list, and the chain
+    /// must be owned by no other list.
+    #[allow(unsafe_code)]
+    unsafe fn splice_nodes(
+        &mut self,
+        prev: Link<T>,
+        next: Link<T>,
+        mut first: NonNull<Node<T>>,
+        mut last: NonNull<Node<T>>,
+        len: usize,
+    ) {
+        // SAFETY: The caller guarantees that all the nodes are valid, and no
+        // reference to them is alive
+        unsafe {
+            match prev {
+                Some(mut prev) => prev.as_mut().next = Some(first),
+                None => self.head = Some(first),
+            }
+            match next {
+                Some(mut next) => next.as_mut().prev = Some(last),
+                None => self.tail = Some(last),
+            }
+            first.as_mut().prev = prev;
+            last.as_mut().next = next;
+        }
+        self.len += len;
+    }
+
+    /// Splits the list right before the given node, which becomes the new
+    /// head, and returns everything that came before it. The whole list is
+    /// returned when the node is `None`.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node of this list, and `at` must be its index.
+    #[allow(unsafe_code)]
+    unsafe fn split_off_before_node(&mut self, node: Link<T>, at: usize) -> DoublyLinkedList<T> {
+        let Some(mut node) = node else {
+            return std::mem::take(self);
+        };
+        // SAFETY: The caller guarantees that the node belongs to the list, so
+        // it and its neighbours are still valid
+        match unsafe { node.as_mut().prev.take() } {
+            None => Self::new(),
+            Some(mut prev) => {
+                unsafe { prev.as_mut().next = None };
+                let front = DoublyLinkedList {
+                    head: self.head,
+                    tail: Some(prev),
+                    len: at,
+                    marker: PhantomData,
+                };
+                self.head = Some(node);
+                self.len -= at;
+                front
+            }
+        }
+    }
+
+    /// Splits the list right after the given node, which becomes the new
+    /// tail, and returns everything that came after it. The whole list is
+    /// returned when the node is `None`.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node of this list, and `at` must be its index.
+    #[allow(unsafe_code)]
+    unsafe fn split_off_after_node(&mut self, node: Link<T>, at: usize) -> DoublyLinkedList<T> {
+        let Some(mut node) = node else {
+            return std::mem::take(self);
+        };
+        // SAFETY: The caller guarantees that the node belongs to the list, so
+        // it and its neighbours are still valid
+        match unsafe { node.as_mut().next.take() } {
+            None => Self::new(),
+            Some(mut next) => {
+                unsafe { next.as_mut().prev = None };
+                let back = DoublyLinkedList {
+                    head: Some(next),
+                    tail: self.tail,
+                    len: self.len - at - 1,
+                    marker: PhantomData,
+                };
+                self.tail = Some(node);
+                self.len = at + 1;
+                back
+            }
+        }
+    }
+}
+
+/// Chains of nodes that are only linked through `next`, while a list is being
+/// sorted or merged. Every node of the list belongs to exactly one of them.
+///
+/// Dropping it links the chains back into the list, restoring the `prev` links
+/// and the tail. The list is sorted once all of its nodes have been merged into
+/// `merged`. If a comparison panics, the list keeps all of its elements but in
+/// an unspecified order.
+struct Chains<'a, T> {
+    list: &'a mut DoublyLinkedList<T>,
+    /// Each run holds either nothing or a sorted chain of 2^i nodes.
+    runs: [Link<T>; usize::BITS as usize],
+    unsorted: Link<T>,
+    /// The two sorted chains to be merged.
+    a: Link<T>,
+    b: Link<T>,
+    merged: Link<T>,
+}
+
+impl<'a, T> Chains<'a, T> {
+    /// Takes all the nodes of the list as one unsorted chain.
+    fn new(list: &'a mut DoublyLinkedList<T>) -> Self {
+        let unsorted = list.head.take();
+        list.tail = None;
+        Self {
+            list,
+            runs: [None; usize::BITS as usize],
+            unsorted,
+            a: None,
+            b: None,
+            merged: None,
+        }
+    }
+
+    /// Merges the sorted chains `a` and `b` into `merged`, which must be
+    /// empty. Nodes of `a` come before equal nodes of `b`.
+    ///
+    /// # Safety
+    ///
+    /// All the chains must hold distinct and valid nodes, and end with a node
+    /// whose `next` is None.
+    #[allow(unsafe_code)]
+    unsafe fn merge<F>(&mut self, compare: &mut F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut tail: Link<T> = None;
+        while let (Some(mut x), Some(mut y)) = (self.a, self.b) {
+            // SAFETY: The caller guarantees that the nodes are valid. A node
+            // is only detached from its chain after the comparison, so that
+            // no node is lost if it panics.
+            let node = unsafe {
+                if compare(&y.as_ref().data, &x.as_ref().data) == Ordering::Less {
+                    self.b = y.as_mut().next.take();
+                    y
+                } else {
+                    self.a = x.as_mut().next.take();
+                    x
+                }
+            };
+            match tail {
+                // SAFETY: The caller guarantees that the nodes are valid
+                Some(mut tail) => unsafe { tail.as_mut().next = Some(node) },
+                None => self.merged = Some(node),
+            }
+            tail = Some(node);
+        }
+        let rest = self.a.take().or(self.b.take());
+        match tail {
+            // SAFETY: The caller guarantees that the nodes are valid
+            Some(mut tail) => unsafe { tail.as_mut().next = rest },
+            None => self.merged = rest,
+        }
+    }
+}
+
+impl<T> Drop for Chains<'_, T> {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        let mut head = None;
+        let mut tail: Link<T> = None;
+        let chains = [self.merged, self.a, self.b, self.unsorted];
+        for mut it_node in chains.into_iter().chain(self.runs) {
+            while let Some(mut node) = it_node {
+                // SAFETY: Every chain holds distinct nodes from the list, so
+                // their raw pointers are still valid
+                unsafe {
+                    node.as_mut().prev = tail;
+                    match tail {
+                        Some(mut tail) => tail.as_mut().next = Some(node),
+                        None => head = Some(node),
+                    }
+                    it_node = node.as_ref().next;
+                }
+                tail = Some(node);
+            }
+        }
+        self.list.head = head;
+        self.list.tail = tail;
+    }
+}
+
+/// An iterator over the elements of a DoublyLinkedList.
+///
+/// This struct is created by [`DoublyLinkedList::iter()`]. See its
+/// documentation for more.
+///
+/// [`DoublyLinkedList::iter()`]: crate::collections::DoublyLinkedList#iter;
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+// SAFETY: The iterator behaves like a `&DoublyLinkedList<T>`.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+
+// SAFETY: Same as above.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[allow(unsafe_code)]
+    fn next(&mut self) -> Option<Self::Item> {
+        // The remaining length is checked instead of the pointers, since the
+        // front and the back of the iterator meet in the middle of the list.
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: Some elements remain, so we know that the raw pointer of the
+        // current Node is still valid
+        self.head.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            self.len -= 1;
+            self.head = node.next;
+            &node.data
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    #[allow(unsafe_code)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: Some elements remain, so we know that the raw pointer of the
+        // current Node is still valid
+        self.tail.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            self.len -= 1;
+            self.tail = node.prev;
+            &node.data
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// A mutable iterator over the elements of a DoublyLinkedList.
+///
+/// This struct is created by [`DoublyLinkedList::iter_mut()`]. See its
+/// documentation for more.
+///
+/// [`DoublyLinkedList::iter_mut()`]: crate::collections::DoublyLinkedList#iter_mut;
+#[derive(Debug)]
+pub struct IterMut<'a, T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    marker: PhantomData<&'a mut Node<T>>,
+}
+
+// SAFETY: The iterator behaves like a `&mut DoublyLinkedList<T>`.
+#[allow(unsafe_code)]
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+
+// SAFETY: Same as above.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[allow(unsafe_code)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: Some elements remain, so we know that the raw pointer of the
+        // current Node is still valid. Each Node is yielded once, so the
+        // mutable references never alias.
+        self.head.map(|node| unsafe {
+            let node = &mut *node.as_ptr();
+            self.len -= 1;
+            self.head = node.next;
+            &mut node.data
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    #[allow(unsafe_code)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: Some elements remain, so we know that the raw pointer of the
+        // current Node is still valid. Each Node is yielded once, so the
+        // mutable references never alias.
+        self.tail.map(|node| unsafe {
+            let node = &mut *node.as_ptr();
+            self.len -= 1;
+            self.tail = node.prev;
+            &mut node.data
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// An owning iterator over the elements of a DoublyLinkedList.
+///
+/// This struct is created by the [`into_iter`] method on DoublyLinkedList
+/// (provided by the [`IntoIterator`] trait). See its documentation for more.
+///
+/// [`into_iter`]: crate::collections::DoublyLinkedList#into_iter;
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    list: DoublyLinkedList<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.list.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for DoublyLinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    /// Consumes the list into an iterator yielding elements by value.
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a DoublyLinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut DoublyLinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator which removes the elements of a DoublyLinkedList that match a
+/// predicate.
+///
+/// This struct is created by [`DoublyLinkedList::extract_if()`]. See its
+/// documentation for more.
+///
+/// [`DoublyLinkedList::extract_if()`]: crate::collections::DoublyLinkedList#extract_if;
+pub struct ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    cursor: CursorMut<'a, T>,
+    filter: F,
+}
+
+impl<T, F> Iterator for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(data) = self.cursor.current() {
+            if (self.filter)(data) {
+                return self.cursor.remove_current();
+            }
+            self.cursor.move_next();
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = match self.cursor.index() {
+            None => 0,
+            Some(index) => self.cursor.list.len - index,
+        };
+        (0, Some(remaining))
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf")
+            .field("cursor", &self.cursor)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A cursor over a DoublyLinkedList.
+///
+/// A cursor is like an iterator, except that it can freely seek
+/// back-and-forth. Cursors always rest between two elements in the list, and
+/// index in a logically circular way. To accommodate this, there is a "ghost"
+/// non-element that yields `None` between the tail and the head of the list.
+///
+/// This struct is created by [`DoublyLinkedList::cursor_front()`] and
+/// [`DoublyLinkedList::cursor_back()`]. See their documentation for more.
+///
+/// [`DoublyLinkedList::cursor_front()`]: crate::collections::DoublyLinkedList#cursor_front;
+/// [`DoublyLinkedList::cursor_back()`]: crate::collections::DoublyLinkedList#cursor_back;
+#[derive(Debug)]
+pub struct Cursor<'a, T> {
+    index: usize,
+    current: Option<NonNull<Node<T>>>,
+    list: &'a DoublyLinkedList<T>,
+}
+
+// SAFETY: The cursor behaves like a `&DoublyLinkedList<T>`.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Send for Cursor<'_, T> {}
+
+// SAFETY: Same as above.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for Cursor<'_, T> {}
+
+impl<T> Clone for Cursor<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index,
+            current: self.current,
+            list: self.list,
+        }
+    }
+}
+
+impl<'a, T> Cursor<'a, T> {
+    /// Returns the index of the cursor position, or None if the cursor is
+    /// pointing to the "ghost" non-element.
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    /// Moves the cursor to the next element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this will
+    /// move it to the first element. If it is pointing to the last element
+    /// then this will move it to the "ghost" non-element.
+    #[allow(unsafe_code)]
+    pub fn move_next(&mut self) {
+        match self.current {
+            None => {
+                self.current = self.list.head;
+                self.index = 0;
+            }
+            Some(current) => {
+                // SAFETY: Current Node is Some, so we know its raw pointer is
+                // still valid
+                self.current = unsafe { current.as_ref().next };
+                self.index += 1;
+            }
+        }
+    }
+
+    /// Moves the cursor to the previous element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this will
+    /// move it to the last element. If it is pointing to the first element
+    /// then this will move it to the "ghost" non-element.
+    #[allow(unsafe_code)]
+    pub fn move_prev(&mut self) {
+        match self.current {
+            None => {
+                self.current = self.list.tail;
+                self.index = self.list.len.saturating_sub(1);
+            }
+            Some(current) => {
+                // SAFETY: Current Node is Some, so we know its raw pointer is
+                // still valid
+                self.current = unsafe { current.as_ref().prev };
+                self.index = self.index.checked_sub(1).unwrap_or(self.list.len);
+            }
+        }
+    }
+
+    /// Returns a reference to the element that the cursor is currently
+    /// pointing to, or None if it is pointing to the "ghost" non-element.
+    #[allow(unsafe_code)]
+    pub fn current(&self) -> Option<&'a T> {
+        // SAFETY: Current Node is Some, so we know its raw pointer is still
+        // valid, and the list is borrowed for 'a
+        self.current
+            .map(|current| unsafe { &(*current.as_ptr()).data })
+    }
+
+    /// Returns a reference to the next element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this returns
+    /// the first element of the list. If it is pointing to the last element
+    /// then this returns None.
+    #[allow(unsafe_code)]
+    pub fn peek_next(&self) -> Option<&'a T> {
+        let next = match self.current {
+            None => self.list.head,
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            Some(current) => unsafe { current.as_ref().next },
+        };
+        // SAFETY: Next Node is Some, so we know its raw pointer is still
+        // valid, and the list is borrowed for 'a
+        next.map(|next| unsafe { &(*next.as_ptr()).data })
+    }
+
+    /// Returns a reference to the previous element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this returns
+    /// the last element of the list. If it is pointing to the first element
+    /// then this returns None.
+    #[allow(unsafe_code)]
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        let prev = match self.current {
+            None => self.list.tail,
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            Some(current) => unsafe { current.as_ref().prev },
+        };
+        // SAFETY: Previous Node is Some, so we know its raw pointer is still
+        // valid, and the list is borrowed for 'a
+        prev.map(|prev| unsafe { &(*prev.as_ptr()).data })
+    }
+}
+
+/// A cursor over a DoublyLinkedList with editing operations.
+///
+/// A cursor is like an iterator, except that it can freely seek
+/// back-and-forth, and can safely mutate the list during iteration. This is
+/// because the lifetime of its yielded references is tied to its own
+/// lifetime, instead of just the underlying list. This means cursors cannot
+/// yield multiple elements at once.
+///
+/// Cursors always rest between two elements in the list, and index in a
+/// logically circular way. To accommodate this, there is a "ghost"
+/// non-element that yields `None` between the tail and the head of the list.
+///
+/// This struct is created by [`DoublyLinkedList::cursor_front_mut()`] and
+/// [`DoublyLinkedList::cursor_back_mut()`]. See their documentation for more.
+///
+/// [`DoublyLinkedList::cursor_front_mut()`]: crate::collections::DoublyLinkedList#cursor_front_mut;
+/// [`DoublyLinkedList::cursor_back_mut()`]: crate::collections::DoublyLinkedList#cursor_back_mut;
+#[derive(Debug)]
+pub struct CursorMut<'a, T> {
+    index: usize,
+    current: Option<NonNull<Node<T>>>,
+    list: &'a mut DoublyLinkedList<T>,
+}
+
+// SAFETY: The cursor behaves like a `&mut DoublyLinkedList<T>`.
+#[allow(unsafe_code)]
+unsafe impl<T: Send> Send for CursorMut<'_, T> {}
+
+// SAFETY: Same as above.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for CursorMut<'_, T> {}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// Returns the index of the cursor position, or None if the cursor is
+    /// pointing to the "ghost" non-element.
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    /// Moves the cursor to the next element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this will
+    /// move it to the first element. If it is pointing to the last element
+    /// then this will move it to the "ghost" non-element.
+    #[allow(unsafe_code)]
+    pub fn move_next(&mut self) {
+        match self.current {
+            None => {
+                self.current = self.list.head;
+                self.index = 0;
+            }
+            Some(current) => {
+                // SAFETY: Current Node is Some, so we know its raw pointer is
+                // still valid
+                self.current = unsafe { current.as_ref().next };
+                self.index += 1;
+            }
+        }
+    }
+
+    /// Moves the cursor to the previous element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this will
+    /// move it to the last element. If it is pointing to the first element
+    /// then this will move it to the "ghost" non-element.
+    #[allow(unsafe_code)]
+    pub fn move_prev(&mut self) {
+        match self.current {
+            None => {
+                self.current = self.list.tail;
+                self.index = self.list.len.saturating_sub(1);
+            }
+            Some(current) => {
+                // SAFETY: Current Node is Some, so we know its raw pointer is
+                // still valid
+                self.current = unsafe { current.as_ref().prev };
+                self.index = self.index.checked_sub(1).unwrap_or(self.list.len);
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the element that the cursor is
+    /// currently pointing to, or None if it is pointing to the "ghost"
+    /// non-element.
+    #[allow(unsafe_code)]
+    pub fn current(&mut self) -> Option<&mut T> {
+        // SAFETY: Current Node is Some, so we know its raw pointer is still
+        // valid, and the cursor is borrowed mutably for as long as the
+        // reference is alive
+        self.current
+            .map(|current| unsafe { &mut (*current.as_ptr()).data })
+    }
+
+    /// Returns a mutable reference to the next element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this returns
+    /// the first element of the list. If it is pointing to the last element
+    /// then this returns None.
+    #[allow(unsafe_code)]
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.current {
+            None => self.list.head,
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            Some(current) => unsafe { current.as_ref().next },
+        };
+        // SAFETY: Next Node is Some, so we know its raw pointer is still
+        // valid, and the cursor is borrowed mutably for as long as the
+        // reference is alive
+        next.map(|next| unsafe { &mut (*next.as_ptr()).data })
+    }
+
+    /// Returns a mutable reference to the previous element.
+    ///
+    /// If the cursor is pointing to the "ghost" non-element then this returns
+    /// the last element of the list. If it is pointing to the first element
+    /// then this returns None.
+    #[allow(unsafe_code)]
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        let prev = match self.current {
+            None => self.list.tail,
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            Some(current) => unsafe { current.as_ref().prev },
+        };
+        // SAFETY: Previous Node is Some, so we know its raw pointer is still
+        // valid, and the cursor is borrowed mutably for as long as the
+        // reference is alive
+        prev.map(|prev| unsafe { &mut (*prev.as_ptr()).data })
+    }
+
+    /// Returns a read-only cursor pointing to the current element.
+    ///
+    /// The lifetime of the returned Cursor is bound to that of the CursorMut,
+    /// which means it cannot outlive the CursorMut and that the CursorMut is
+    /// frozen for the lifetime of the Cursor.
+    pub fn as_cursor(&self) -> Cursor<'_, T> {
+        Cursor {
+            index: self.index,
+            current: self.current,
+            list: self.list,
+        }
+    }
+
+    /// Returns the nodes in between which something is inserted after the
+    /// cursor.
+    #[allow(unsafe_code)]
+    fn after(&self) -> (Link<T>, Link<T>) {
+        match self.current {
+            None => (None, self.list.head),
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            Some(current) => (Some(current), unsafe { current.as_ref().next }),
+        }
+    }
+
+    /// Returns the nodes in between which something is inserted before the
+    /// cursor.
+    #[allow(unsafe_code)]
+    fn before(&self) -> (Link<T>, Link<T>) {
+        match self.current {
+            None => (self.list.tail, None),
+            // SAFETY: Current Node is Some, so we know its raw pointer is
+            // still valid
+            Some(current) => (unsafe { current.as_ref().prev }, Some(current)),
+        }
+    }
+
+    /// Inserts a new element into the list after the current one.
+    ///
+    /// If the cursor is pointing at the "ghost" non-element then the new
+    /// element is inserted at the front of the list.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn insert_after(&mut self, data: T) {
+        let node = NonNull::from(Box::leak(Box::new(Node::new(data))));
+        let (prev, next) = self.after();
+        // SAFETY: The neighbours are adjacent nodes of the list, and the new
+        // node has just been allocated
+        unsafe { self.list.splice_nodes(prev, next, node, node, 1) };
+        if self.current.is_none() {
+            // The "ghost" non-element index has changed.
+            self.index = self.list.len;
+        }
+    }
+
+    /// Inserts a new element into the list before the current one.
+    ///
+    /// If the cursor is pointing at the "ghost" non-element then the new
+    /// element is inserted at the end of the list.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn insert_before(&mut self, data: T) {
+        let node = NonNull::from(Box::leak(Box::new(Node::new(data))));
+        let (prev, next) = self.before();
+        // SAFETY: The neighbours are adjacent nodes of the list, and the new
+        // node has just been allocated
+        unsafe { self.list.splice_nodes(prev, next, node, node, 1) };
+        self.index += 1;
+    }
+
+    /// Removes the current element from the list and returns it, or None if
+    /// the cursor is pointing to the "ghost" non-element.
+    ///
+    /// The cursor is moved to point to the next element in the list.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn remove_current(&mut self) -> Option<T> {
+        let current = self.current?;
+        // SAFETY: Current Node is Some, so we know it belongs to the list and
+        // that its raw pointer is still valid. Once unlinked, nothing points
+        // to the node anymore so we can take back its ownership.
+        unsafe {
+            self.current = current.as_ref().next;
+            self.list.unlink_node(current);
+            Some(Box::from_raw(current.as_ptr()).data)
+        }
+    }
+
+    /// Inserts the elements from the given list after the current one.
+    ///
+    /// If the cursor is pointing at the "ghost" non-element then the new
+    /// elements are inserted at the start of the list.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn splice_after(&mut self, mut list: DoublyLinkedList<T>) {
+        let (Some(first), Some(last)) = (list.head.take(), list.tail.take()) else {
+            return;
+        };
+        let len = std::mem::take(&mut list.len);
+        let (prev, next) = self.after();
+        // SAFETY: The neighbours are adjacent nodes of the list, and the
+        // spliced nodes were taken away from the other list
+        unsafe { self.list.splice_nodes(prev, next, first, last, len) };
+        if self.current.is_none() {
+            // The "ghost" non-element index has changed.
+            self.index = self.list.len;
+        }
+    }
+
+    /// Inserts the elements from the given list before the current one.
+    ///
+    /// If the cursor is pointing at the "ghost" non-element then the new
+    /// elements are inserted at the end of the list.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn splice_before(&mut self, mut list: DoublyLinkedList<T>) {
+        let (Some(first), Some(last)) = (list.head.take(), list.tail.take()) else {
+            return;
+        };
+        let len = std::mem::take(&mut list.len);
+        let (prev, next) = self.before();
+        // SAFETY: The neighbours are adjacent nodes of the list, and the
+        // spliced nodes were taken away from the other list
+        unsafe { self.list.splice_nodes(prev, next, first, last, len) };
+        self.index += len;
+    }
+
+    /// Splits the list into two after the current element. This will return
+    /// a new list consisting of everything after the cursor, with the
+    /// original list retaining everything before.
+    ///
+    /// If the cursor is pointing at the "ghost" non-element then the entire
+    /// contents of the list are moved.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn split_after(&mut self) -> DoublyLinkedList<T> {
+        // SAFETY: The current node belongs to the list, at the cursor index
+        let back = unsafe { self.list.split_off_after_node(self.current, self.index) };
+        if self.current.is_none() {
+            // The "ghost" non-element index has changed.
+            self.index = self.list.len;
+        }
+        back
+    }
+
+    /// Splits the list into two before the current element. This will return
+    /// a new list consisting of everything before the cursor, with the
+    /// original list retaining everything after.
+    ///
+    /// If the cursor is pointing at the "ghost" non-element then the entire
+    /// contents of the list are moved.
+    ///
+    /// This operation should compute in O(1) time.
+    #[allow(unsafe_code)]
+    pub fn split_before(&mut self) -> DoublyLinkedList<T> {
+        // SAFETY: The current node belongs to the list, at the cursor index
+        let front = unsafe { self.list.split_off_before_node(self.current, self.index) };
+        self.index = 0;
+        front
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the elements of the list, after checking that walking it
+    /// backward gives them in the reverse order.
+    fn check_links<T: Clone + PartialEq + std::fmt::Debug>(list: &DoublyLinkedList<T>) -> Vec<T> {
+        let forward: Vec<T> = list.iter().cloned().collect();
+        let mut backward = Vec::new();
+        let mut cursor = list.cursor_back();
+        while let Some(data) = cursor.current() {
+            backward.push(data.clone());
+            cursor.move_prev();
+        }
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward.len(), list.len());
+        forward
+    }
+
+    #[test]
+    fn push_front_pop_front() {
+        let mut ll = DoublyLinkedList::new();
+        assert!(ll.is_empty());
+        assert_eq!(ll.len(), 0);
+
+        ll.push_front(0);
+        ll.push_front(1);
+        ll.push_front(2);
+
+        assert!(!ll.is_empty());
+        assert_eq!(ll.len(), 3);
+
+        assert_eq!(ll.pop_front(), Some(2));
+        assert_eq!(ll.pop_front(), Some(1));
+        assert_eq!(ll.pop_front(), Some(0));
+        assert_eq!(ll.pop_front(), None);
+
+        assert!(ll.is_empty());
+        assert_eq!(ll.len(), 0);
+    }
+
+    #[test]
+    fn push_front_pop_back() {
+        let mut ll = DoublyLinkedList::new();
+        assert!(ll.is_empty());
+        assert_eq!(ll.len(), 0);
+
+        ll.push_front(0);
+        ll.push_front(1);
+        ll.push_front(2);
+
+        assert!(!ll.is_empty());
+        assert_eq!(ll.len(), 3);
+
+        assert_eq!(ll.pop_back(), Some(0));
+        assert_eq!(ll.pop_back(), Some(1));
+        assert_eq!(ll.pop_back(), Some(2));
+        assert_eq!(ll.pop_back(), None);
+
+        assert!(ll.is_empty());
+        assert_eq!(ll.len(), 0);
+    }
+
+    #[test]
+    fn push_back_pop_back() {
+        let mut ll = DoublyLinkedList::new();
+        assert!(ll.is_empty());
+        assert_eq!(ll.len(), 0);
 
         ll.push_back(0);
         ll.push_back(1);
@@ -758,4 +2399,419 @@ mod tests {
         assert!(ll.is_empty());
         assert_eq!(ll.len(), 0);
     }
+
+    #[test]
+    fn append_keeps_links() {
+        let mut ll = DoublyLinkedList::new();
+        let mut other = DoublyLinkedList::new();
+        other.push_back(0);
+        ll.append(&mut other);
+        other.push_back(1);
+        other.push_back(2);
+        ll.append(&mut other);
+        ll.append(&mut DoublyLinkedList::new());
+        assert!(other.is_empty());
+        assert_eq!(ll.len(), 3);
+        assert_eq!(ll.back(), Some(&2));
+
+        assert_eq!(ll.pop_back(), Some(2));
+        assert_eq!(ll.pop_back(), Some(1));
+        assert_eq!(ll.pop_back(), Some(0));
+        assert_eq!(ll.pop_back(), None);
+        assert!(ll.is_empty());
+    }
+
+    #[test]
+    fn cursor_wraps_around_the_ghost() {
+        let ll: DoublyLinkedList<i32> = DoublyLinkedList::new();
+        let mut cursor = ll.cursor_front();
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+        cursor.move_next();
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+
+        let mut ll = DoublyLinkedList::new();
+        ll.push_back(0);
+        ll.push_back(1);
+        let mut cursor = ll.cursor_front();
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(
+            (cursor.peek_prev(), cursor.peek_next()),
+            (Some(&1), Some(&0))
+        );
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (Some(1), Some(&1)));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&0)));
+        assert_eq!(cursor.peek_prev(), None);
+    }
+
+    #[test]
+    fn cursor_mut_inserts_and_removes() {
+        let mut ll = DoublyLinkedList::new();
+        let mut cursor = ll.cursor_front_mut();
+        cursor.insert_after(2);
+        cursor.insert_before(3);
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        cursor.insert_before(1);
+        cursor.insert_after(5);
+        assert_eq!((cursor.index(), cursor.current()), (Some(1), Some(&mut 2)));
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!((cursor.index(), cursor.current()), (Some(1), Some(&mut 5)));
+        *cursor.peek_next().unwrap() += 1;
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+        assert_eq!(cursor.remove_current(), None);
+        assert_eq!(check_links(&ll), [1, 5]);
+
+        let mut cursor = ll.cursor_back_mut();
+        assert_eq!(cursor.remove_current(), Some(5));
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert!(ll.is_empty());
+        assert_eq!(check_links(&ll), []);
+    }
+
+    #[test]
+    fn cursor_mut_splices_and_splits() {
+        let mut ll = DoublyLinkedList::new();
+        ll.push_back(2);
+        ll.push_back(5);
+        let mut other = DoublyLinkedList::new();
+        other.push_back(3);
+        other.push_back(4);
+
+        let mut cursor = ll.cursor_front_mut();
+        cursor.splice_after(other.split_off(0));
+        cursor.splice_before(DoublyLinkedList::new());
+        cursor.move_prev();
+        other.push_back(0);
+        other.push_back(1);
+        cursor.splice_after(other.split_off(0));
+        other.push_back(6);
+        cursor.splice_before(other.split_off(0));
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (Some(6), Some(&mut 6)));
+        assert_eq!(check_links(&ll), [0, 1, 2, 3, 4, 5, 6]);
+
+        let mut cursor = ll.cursor_front_mut();
+        cursor.move_next();
+        cursor.move_next();
+        let front = cursor.split_before();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 2)));
+        cursor.move_next();
+        let back = cursor.split_after();
+        assert_eq!((cursor.index(), cursor.current()), (Some(1), Some(&mut 3)));
+        assert_eq!(cursor.split_after().len(), 0);
+        cursor.move_next();
+        assert_eq!(cursor.split_after().len(), 2);
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.split_before().len(), 0);
+        assert!(ll.is_empty());
+        assert_eq!(check_links(&front), [0, 1]);
+        assert_eq!(check_links(&back), [4, 5, 6]);
+
+        let mut ll = DoublyLinkedList::new();
+        ll.push_back(0);
+        ll.push_back(1);
+        ll.push_back(2);
+        let mut split = ll.split_off(1);
+        assert_eq!(check_links(&ll), [0]);
+        assert_eq!(check_links(&split), [1, 2]);
+        assert_eq!(check_links(&split.split_off(2)), []);
+        assert_eq!(check_links(&split.split_off(0)), [1, 2]);
+    }
+
+    #[test]
+    fn iterators_meet_in_the_middle() {
+        let mut ll = DoublyLinkedList::new();
+        for i in 0..5 {
+            ll.push_back(i);
+        }
+
+        let mut iter = ll.iter();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!((iter.next(), iter.next_back()), (Some(&0), Some(&4)));
+        assert_eq!((iter.next(), iter.next_back()), (Some(&1), Some(&3)));
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.clone().next_back(), Some(&2));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!((iter.next(), iter.next_back()), (None, None));
+        assert_eq!(iter.len(), 0);
+
+        let mut iter = ll.iter_mut();
+        while let (Some(front), Some(back)) = (iter.next(), iter.next_back()) {
+            std::mem::swap(front, back);
+        }
+        assert_eq!(
+            ll.iter().rev().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(ll.iter_mut().rev().len(), 5);
+    }
+
+    #[test]
+    fn into_iter_owns_the_elements() {
+        let rc = std::rc::Rc::new(());
+        let mut ll = DoublyLinkedList::new();
+        for _ in 0..4 {
+            ll.push_back(rc.clone());
+        }
+        let mut iter = ll.into_iter();
+        assert_eq!(iter.len(), 4);
+        drop((iter.next(), iter.next_back()));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(std::rc::Rc::strong_count(&rc), 3);
+        drop(iter);
+        assert_eq!(std::rc::Rc::strong_count(&rc), 1);
+
+        let mut ll = DoublyLinkedList::new();
+        ll.push_back(1);
+        ll.push_back(2);
+        for data in &mut ll {
+            *data *= 10;
+        }
+        let mut sum = 0;
+        for data in &ll {
+            sum += data;
+        }
+        assert_eq!(sum, 30);
+        assert_eq!(ll.into_iter().rev().collect::<Vec<_>>(), [20, 10]);
+    }
+
+    #[test]
+    fn collects_and_extends() {
+        let mut ll: DoublyLinkedList<_> = (0..3).collect();
+        ll.extend(vec![3, 4]);
+        ll.extend(&[5]);
+        assert_eq!(check_links(&ll), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(ll, DoublyLinkedList::from([0, 1, 2, 3, 4, 5]));
+        assert_eq!(ll, DoublyLinkedList::from(vec![0, 1, 2, 3, 4, 5]));
+        assert_eq!(format!("{ll:?}"), "[0, 1, 2, 3, 4, 5]");
+        assert_eq!(format!("{:?}", DoublyLinkedList::<i32>::new()), "[]");
+    }
+
+    #[test]
+    fn compares_and_hashes_like_a_sequence() {
+        use std::collections::HashSet;
+
+        let short = DoublyLinkedList::from([1, 2]);
+        let long = DoublyLinkedList::from([1, 2, 3]);
+        let greater = DoublyLinkedList::from([1, 3]);
+        assert_ne!(short, long);
+        assert!(short < long && long < greater);
+        assert_eq!(short.cmp(&short.clone()), Ordering::Equal);
+        assert_eq!(
+            DoublyLinkedList::from([f64::NAN]).partial_cmp(&DoublyLinkedList::from([0.0])),
+            None
+        );
+
+        let set: HashSet<_> = [short.clone(), long, short].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn clone_from_reuses_nodes() {
+        let source = DoublyLinkedList::from([String::from("a"), String::from("b")]);
+
+        let mut ll = DoublyLinkedList::from(vec![String::from("x"); 4]);
+        let front: *const String = ll.front().unwrap();
+        ll.clone_from(&source);
+        assert!(std::ptr::eq(front, ll.front().unwrap()));
+        assert_eq!(check_links(&ll), source.iter().cloned().collect::<Vec<_>>());
+
+        let mut ll = DoublyLinkedList::from([String::from("x")]);
+        let front: *const String = ll.front().unwrap();
+        ll.clone_from(&source);
+        assert!(std::ptr::eq(front, ll.front().unwrap()));
+        assert_eq!(ll, source);
+        assert_eq!(check_links(&ll.clone()), ["a", "b"]);
+    }
+
+    #[test]
+    fn auto_traits_follow_the_elements() {
+        fn is_send<T: Send>() {}
+        fn is_sync<T: Sync>() {}
+        is_send::<DoublyLinkedList<i32>>();
+        is_sync::<DoublyLinkedList<i32>>();
+        is_send::<Iter<'_, i32>>();
+        is_sync::<Iter<'_, i32>>();
+        is_send::<IterMut<'_, i32>>();
+        is_sync::<IterMut<'_, i32>>();
+        is_send::<IntoIter<i32>>();
+        is_sync::<IntoIter<i32>>();
+        is_send::<Cursor<'_, i32>>();
+        is_send::<CursorMut<'_, i32>>();
+        // Send but not Sync, moving the list moves the elements along.
+        is_send::<DoublyLinkedList<std::cell::Cell<i32>>>();
+        is_send::<IterMut<'_, std::cell::Cell<i32>>>();
+
+        let ll = DoublyLinkedList::from([1, 2, 3]);
+        let ll = std::thread::spawn(move || ll.into_iter().rev().collect::<DoublyLinkedList<_>>())
+            .join()
+            .unwrap();
+        assert_eq!(check_links(&ll), [3, 2, 1]);
+    }
+
+    #[test]
+    fn indexed_access_from_both_ends() {
+        let mut ll: DoublyLinkedList<_> = (0..7).collect();
+        for i in 0..7 {
+            assert_eq!(ll.get(i), Some(&i));
+        }
+        assert_eq!(ll.get(7), None);
+        *ll.get_mut(5).unwrap() *= 10;
+        *ll.get_mut(1).unwrap() *= 10;
+        assert_eq!(ll.get_mut(8), None);
+
+        ll.insert(0, 100);
+        ll.insert(8, 200);
+        ll.insert(6, 300);
+        assert_eq!(check_links(&ll), [100, 0, 10, 2, 3, 4, 300, 50, 6, 200]);
+
+        ll.swap(0, 9);
+        ll.swap(3, 3);
+        ll.swap(7, 2);
+        assert_eq!(check_links(&ll), [200, 0, 50, 2, 3, 4, 300, 10, 6, 100]);
+
+        assert_eq!(ll.remove(9), Some(100));
+        assert_eq!(ll.remove(0), Some(200));
+        assert_eq!(ll.remove(5), Some(300));
+        assert_eq!(ll.remove(7), None);
+        assert_eq!(check_links(&ll), [0, 50, 2, 3, 4, 10, 6]);
+
+        let mut back = ll.split_off(5);
+        assert_eq!(check_links(&back.split_off(1)), [6]);
+        assert_eq!(check_links(&back), [10]);
+        assert_eq!(check_links(&ll.split_off(1)), [50, 2, 3, 4]);
+        assert_eq!(check_links(&ll), [0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot insert at a nonexistent index")]
+    fn insert_out_of_bounds() {
+        DoublyLinkedList::from([0]).insert(2, 1);
+    }
+
+    #[test]
+    fn retain_and_extract_if() {
+        let mut ll: DoublyLinkedList<_> = (0..10).collect();
+        ll.retain(|&x| x % 3 != 0);
+        assert_eq!(check_links(&ll), [1, 2, 4, 5, 7, 8]);
+        ll.retain_mut(|x| {
+            *x *= 2;
+            *x > 4
+        });
+        assert_eq!(check_links(&ll), [8, 10, 14, 16]);
+
+        let mut extract = ll.extract_if(|x| *x % 4 == 0);
+        assert_eq!(extract.size_hint(), (0, Some(4)));
+        assert_eq!(extract.next(), Some(8));
+        assert_eq!(extract.next(), Some(16));
+        assert_eq!(extract.next(), None);
+        assert_eq!(check_links(&ll), [10, 14]);
+
+        // Elements that were not visited are kept.
+        let mut ll: DoublyLinkedList<_> = (0..6).collect();
+        assert_eq!(ll.extract_if(|_| true).nth(1), Some(1));
+        assert_eq!(check_links(&ll), [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn reverse_relinks_both_ways() {
+        let mut ll: DoublyLinkedList<_> = (0..5).collect();
+        ll.reverse();
+        assert_eq!(check_links(&ll), [4, 3, 2, 1, 0]);
+        ll.push_back(-1);
+        ll.push_front(5);
+        assert_eq!(check_links(&ll), [5, 4, 3, 2, 1, 0, -1]);
+
+        let mut ll: DoublyLinkedList<i32> = DoublyLinkedList::new();
+        ll.reverse();
+        assert!(check_links(&ll).is_empty());
+    }
+
+    #[test]
+    fn sort_is_stable_and_keeps_nodes() {
+        // A simple linear congruential generator, so that the test is
+        // reproducible without depending on a random number crate.
+        let mut seed = 42u64;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as u32
+        };
+        for len in [0, 1, 2, 3, 7, 64, 100, 1000] {
+            let pairs: Vec<_> = (0..len).map(|i| (next() % 16, i)).collect();
+            let mut ll: DoublyLinkedList<_> = pairs.iter().copied().collect();
+            let mut expected = pairs;
+            expected.sort_by_key(|&(key, _)| key);
+
+            let nodes: std::collections::HashSet<_> =
+                ll.iter().map(|pair| pair as *const _).collect();
+            ll.sort_by_key(|&(key, _)| key);
+            assert_eq!(check_links(&ll), expected);
+            assert!(ll.iter().all(|pair| nodes.contains(&(pair as *const _))));
+
+            ll.sort_by(|a, b| b.cmp(a));
+            expected.reverse();
+            assert_eq!(check_links(&ll), expected);
+        }
+    }
+
+    #[test]
+    fn merge_sorted_lists() {
+        let mut ll = DoublyLinkedList::from([(1, 'a'), (3, 'a'), (3, 'b')]);
+        let mut other = DoublyLinkedList::from([(0, 'c'), (3, 'a'), (4, 'c')]);
+        ll.merge(&mut other);
+        assert!(check_links(&other).is_empty());
+        assert_eq!(
+            check_links(&ll),
+            [(0, 'c'), (1, 'a'), (3, 'a'), (3, 'a'), (3, 'b'), (4, 'c')]
+        );
+
+        let mut empty = DoublyLinkedList::new();
+        empty.merge(&mut ll);
+        ll.merge(&mut DoublyLinkedList::new());
+        assert_eq!(empty.len(), 6);
+        assert!(ll.is_empty());
+        assert_eq!(check_links(&empty).last(), Some(&(4, 'c')));
+    }
+
+    #[test]
+    fn dedup_removes_consecutive_repeats() {
+        let mut ll = DoublyLinkedList::from([1, 1, 2, 3, 3, 3, 1, 4, 4]);
+        ll.dedup();
+        assert_eq!(check_links(&ll), [1, 2, 3, 1, 4]);
+        ll.dedup_by_key(|x| *x / 2);
+        assert_eq!(check_links(&ll), [1, 2, 1, 4]);
+
+        let mut ll = DoublyLinkedList::from([String::from("a"), String::from("A")]);
+        ll.dedup_by(|a, b| {
+            b.push_str(a);
+            a.eq_ignore_ascii_case(b.get(..1).unwrap())
+        });
+        assert_eq!(check_links(&ll), ["aA"]);
+    }
+
+    #[test]
+    fn panicking_comparison_keeps_elements() {
+        let mut ll: DoublyLinkedList<_> = (0..100).rev().collect();
+        let mut comparisons = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ll.sort_by(|a, b| {
+                comparisons += 1;
+                assert!(comparisons < 300, "comparison failed");
+                a.cmp(b)
+            })
+        }));
+        assert!(result.is_err());
+        let mut elements = check_links(&ll);
+        elements.sort();
+        assert_eq!(elements, (0..100).collect::<Vec<_>>());
+    }
 }
@@ -1,10 +1,30 @@
 use std::borrow::Borrow;
 use std::collections::hash_map::RandomState;
+use std::fmt;
 use std::hash::{BuildHasher, Hash, Hasher};
 use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::ops::Index;
+use std::ptr::NonNull;
 
-/// A basic hash map.
+/// A hash map that remembers the order in which its entries were inserted.
+///
+/// Every entry is linked into a doubly linked list that runs through the whole
+/// table, so iterating over the map yields its entries in insertion order.
+/// That order is not affected by the table being resized, and re-inserting an
+/// existing key does not move it. Removing an entry unlinks it in O(1) time.
+///
+/// The map can instead be put in access order with [`set_access_order`], in
+/// which case accessing an entry through a mutable reference to the map, e.g.
+/// with [`get_refresh`], moves it to the back, so the front of the map holds
+/// the least recently used entry. Together with a capacity limit, which evicts
+/// the front entry whenever the map grows past it, this turns the map into an
+/// LRU cache. Lookups through a shared reference, such as [`get`], leave the
+/// order untouched.
+///
+/// [`set_access_order`]: LinkedHashMap::set_access_order
+/// [`get_refresh`]: LinkedHashMap::get_refresh
+/// [`get`]: LinkedHashMap::get
 ///
 /// It is required that the keys implement the [`Eq`] and [`Hash`] traits,
 /// although this can frequently be achieved by using
@@ -156,25 +176,88 @@ use std::ops::Index;
 ///     .iter().cloned().collect();
 /// // use the values stored in map
 /// ```
-#[derive(Debug)]
+///
+/// A LinkedHashMap in access order with a capacity limit can be used as an
+/// LRU cache.
+///
+/// ```
+/// use rusty_crust::collections::LinkedHashMap;
+///
+/// let mut cache = LinkedHashMap::with_capacity_limit(2);
+/// cache.set_access_order(true);
+///
+/// cache.insert("a", 1);
+/// cache.insert("b", 2);
+/// // "a" becomes the most recently used entry.
+/// assert_eq!(cache.get_refresh("a"), Some(&1));
+/// // "b" is the least recently used entry and gets evicted.
+/// cache.insert("c", 3);
+///
+/// assert!(!cache.contains_key("b"));
+/// let keys: Vec<_> = cache.keys().collect();
+/// assert_eq!(keys, [&"a", &"c"]);
+/// ```
 pub struct LinkedHashMap<K, V, S = RandomState> {
     // This hash map implementation relies on an array of buckets that is
     // indexed by the hash of an entry's key. If 2 different keys are hashed to
     // the same value, the entries are put into the same bucket. These entries
     // can later be retrieved by comparing both the hashed key and the actual
     // key.
+    //
+    // The buckets only hold pointers to the entries, which are allocated on
+    // the heap and chained together from `head` to `tail` in the order they
+    // were inserted. Resizing the array of buckets only moves the pointers
+    // around, so the chain and the iteration order are left untouched.
     buckets: Vec<Bucket<K, V>>,
+    head: Option<NonNull<Node<K, V>>>,
+    tail: Option<NonNull<Node<K, V>>>,
     hasher_builder: S,
     entries_count: usize,
+    access_order: bool,
+    capacity_limit: Option<usize>,
+    on_evict: Option<Box<dyn FnMut(K, V) + Send>>,
+    marker: PhantomData<Box<Node<K, V>>>,
+}
+
+// SAFETY: The map owns all of its nodes, so sending it to another thread is
+// the same as sending its keys, values, and hasher builder. The eviction
+// callback is required to be `Send`.
+unsafe impl<K, V, S> Send for LinkedHashMap<K, V, S>
+where
+    K: Send,
+    V: Send,
+    S: Send,
+{
+}
+
+// SAFETY: Shared references to the map only give out shared references to
+// its keys, values, and hasher builder. The links between the nodes and the
+// eviction callback are only touched through a mutable reference.
+unsafe impl<K, V, S> Sync for LinkedHashMap<K, V, S>
+where
+    K: Sync,
+    V: Sync,
+    S: Sync,
+{
+}
+
+/// An entry of [`LinkedHashMap`] that is linked to the entries inserted right
+/// before and right after it.
+///
+/// [`LinkedHashMap`]: crate::collections::LinkedHashMap
+struct Node<K, V> {
+    prev: Option<NonNull<Node<K, V>>>,
+    next: Option<NonNull<Node<K, V>>>,
+    key: K,
+    value: V,
 }
 
 /// A data item that holds entries in [`LinkedHashMap`] whose key is hashed to
 /// the same value.
 ///
 /// [`LinkedHashMap`]: crate::collections::LinkedHashMap
-#[derive(Debug)]
 struct Bucket<K, V> {
-    items: Vec<(K, V)>,
+    items: Vec<NonNull<Node<K, V>>>,
 }
 
 impl<K, V> Default for Bucket<K, V> {
@@ -197,12 +280,41 @@ impl<K, V> Default for LinkedHashMap<K, V, RandomState> {
     fn default() -> Self {
         Self {
             buckets: Vec::new(),
+            head: None,
+            tail: None,
             hasher_builder: RandomState::new(),
             entries_count: 0,
+            access_order: false,
+            capacity_limit: None,
+            on_evict: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> Drop for LinkedHashMap<K, V, S> {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        let mut it = self.head;
+        while let Some(node) = it {
+            // SAFETY: Every node in the chain is owned by the map and is
+            // deallocated exactly once here.
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            it = node.next;
         }
     }
 }
 
+impl<K, V, S> fmt::Debug for LinkedHashMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
 impl<K, V> LinkedHashMap<K, V, RandomState> {
     /// Creates an empty `LinkedHashMap`.
     ///
@@ -218,6 +330,60 @@ impl<K, V> LinkedHashMap<K, V, RandomState> {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Creates an empty `LinkedHashMap` in access order.
+    ///
+    /// See [`set_access_order`] for what changes in this mode.
+    ///
+    /// [`set_access_order`]: LinkedHashMap::set_access_order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::with_access_order();
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// map.get_refresh(&1);
+    ///
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&2, &1]);
+    /// ```
+    pub fn with_access_order() -> Self {
+        let mut map = Self::new();
+        map.access_order = true;
+        map
+    }
+
+    /// Creates an empty `LinkedHashMap` that holds at most `limit` entries.
+    ///
+    /// Whenever an insertion makes the map exceed the limit, the entry at the
+    /// front of the map is evicted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::with_capacity_limit(2);
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// map.insert(3, "c");
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.get(&1), None);
+    /// ```
+    pub fn with_capacity_limit(limit: usize) -> Self {
+        assert!(limit > 0, "Capacity limit must be greater than zero");
+        let mut map = Self::new();
+        map.capacity_limit = Some(limit);
+        map
+    }
 }
 
 impl<K, V, S> LinkedHashMap<K, V, S> {
@@ -252,6 +418,210 @@ impl<K, V, S> LinkedHashMap<K, V, S> {
     pub fn is_empty(&self) -> bool {
         self.entries_count == 0
     }
+
+    /// Returns true if the map is in access order.
+    pub fn is_access_order(&self) -> bool {
+        self.access_order
+    }
+
+    /// Switches the map between insertion order and access order.
+    ///
+    /// In access order, [`get_refresh`], [`get_mut`], [`entry`] on an existing
+    /// key, and [`insert`] on an existing key move the entry to the back of the
+    /// map. The front of the map then holds the least recently used entry.
+    /// Lookups through a shared reference, such as [`get`], and iterating over
+    /// the map do not count as an access.
+    ///
+    /// Switching modes does not reorder the existing entries.
+    ///
+    /// [`get`]: LinkedHashMap::get
+    /// [`get_refresh`]: LinkedHashMap::get_refresh
+    /// [`get_mut`]: LinkedHashMap::get_mut
+    /// [`entry`]: LinkedHashMap::entry
+    /// [`insert`]: LinkedHashMap::insert
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::new();
+    /// map.set_access_order(true);
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// map.insert(1, "c");
+    ///
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&2, &1]);
+    /// ```
+    pub fn set_access_order(&mut self, access_order: bool) {
+        self.access_order = access_order;
+    }
+
+    /// Returns the maximum number of entries the map holds, if there is one.
+    pub fn capacity_limit(&self) -> Option<usize> {
+        self.capacity_limit
+    }
+
+    /// Sets a function that is called with every entry evicted because the map
+    /// exceeded its capacity limit. Entries removed in any other way are not
+    /// passed to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    /// use std::sync::mpsc;
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// let mut map = LinkedHashMap::with_capacity_limit(1);
+    /// map.set_eviction_callback(move |k, v| tx.send((k, v)).unwrap());
+    ///
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// assert_eq!(rx.try_recv(), Ok((1, "a")));
+    /// ```
+    pub fn set_eviction_callback<F>(&mut self, f: F)
+    where
+        F: FnMut(K, V) + Send + 'static,
+    {
+        self.on_evict = Some(Box::new(f));
+    }
+
+    /// An iterator visiting all key-value pairs in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// let mut iter = map.iter();
+    /// assert_eq!(iter.next(), Some((&"a", &1)));
+    /// assert_eq!(iter.next(), Some((&"b", &2)));
+    /// assert_eq!(iter.next(), Some((&"c", &3)));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            it: self.head,
+            len: self.entries_count,
+            marker: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all keys in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&"a", &"b", &"c"]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// An iterator visiting all values in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// let values: Vec<_> = map.values().collect();
+    /// assert_eq!(values, [&1, &2, &3]);
+    /// ```
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    /// Allocate a new entry, append it to the back of the chain of entries,
+    /// and add it to the bucket at `bucket_idx`.
+    #[allow(unsafe_code)]
+    fn push_entry(
+        &mut self,
+        bucket_idx: usize,
+        key: K,
+        value: V,
+    ) -> NonNull<Node<K, V>> {
+        let node = Box::new(Node {
+            prev: None,
+            next: None,
+            key,
+            value,
+        });
+        // SAFETY: `Box::into_raw` does not give a null pointer.
+        let node = unsafe { NonNull::new_unchecked(Box::into_raw(node)) };
+        self.link_back(node);
+        self.buckets[bucket_idx].items.push(node);
+        self.entries_count += 1;
+        node
+    }
+
+    /// Attach a detached node to the back of the chain of entries.
+    #[allow(unsafe_code)]
+    fn link_back(&mut self, mut node: NonNull<Node<K, V>>) {
+        // SAFETY: The node is owned by the map and is not linked to any other
+        // node, so we are free to modify its links.
+        unsafe {
+            node.as_mut().prev = self.tail;
+            node.as_mut().next = None;
+        }
+        match self.tail {
+            None => self.head = Some(node),
+            // SAFETY: Tail is not None so we know the raw pointer inside is
+            // still valid.
+            Some(mut tail) => unsafe { tail.as_mut().next = Some(node) },
+        }
+        self.tail = Some(node);
+    }
+
+    /// Detach a node from the chain of entries, linking its neighbours to each
+    /// other.
+    #[allow(unsafe_code)]
+    fn unlink(&mut self, mut node: NonNull<Node<K, V>>) {
+        // SAFETY: The node is owned by the map and is part of the chain, so
+        // it and its neighbours are still valid.
+        unsafe {
+            let node = node.as_mut();
+            match node.prev {
+                None => self.head = node.next,
+                Some(mut prev) => prev.as_mut().next = node.next,
+            }
+            match node.next {
+                None => self.tail = node.prev,
+                Some(mut next) => next.as_mut().prev = node.prev,
+            }
+            node.prev = None;
+            node.next = None;
+        }
+    }
+
+    /// Record an access to the entry held by `node`, moving it to the back of
+    /// the chain if the map is in access order.
+    fn touch(&mut self, node: NonNull<Node<K, V>>) {
+        if self.access_order && self.tail != Some(node) {
+            self.unlink(node);
+            self.link_back(node);
+        }
+    }
 }
 
 impl<K, V, S> LinkedHashMap<K, V, S>
@@ -261,11 +631,17 @@ where
 {
     /// Inserts a key-value pair into the map.
     ///
-    /// If the map did not have this key present, [`None`] is returned.
+    /// If the map did not have this key present, [`None`] is returned, and the
+    /// entry is placed after every other entry in the iteration order.
+    ///
+    /// If the map did have this key present, the value is updated, and the old
+    /// value is returned.  The key is not updated, though; this matters for
+    /// types that can be `==` without being identical. The position of the
+    /// entry in the iteration order is not changed either, unless the map is
+    /// in access order, in which case the entry is moved to the back.
     ///
-    /// If the map did have this key present, the value is updated, and the old 
-    /// value is returned.  The key is not updated, though; this matters for 
-    /// types that can be `==` without being identical.
+    /// Inserting a new key into a map that is at its capacity limit evicts the
+    /// entry at the front of the map.
     ///
     /// # Examples
     ///
@@ -280,6 +656,7 @@ where
     /// assert_eq!(map.insert(37, "c"), Some("b"));
     /// assert_eq!(map[&37], "c");
     /// ```
+    #[allow(unsafe_code)]
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         if self.buckets.is_empty()
             || self.entries_count > 3 * self.buckets.len() / 4
@@ -287,24 +664,35 @@ where
             self.grow();
         }
 
-        let idx = self.index(&key);
-        let bucket = &mut self.buckets[idx];
-
-        for &mut (ref k, ref mut v) in bucket.items.iter_mut() {
-            if *k == key {
-                return Some(std::mem::replace(v, value));
-            }
+        let bucket_idx = self.index(&key);
+        if let Some(mut node) = self.buckets[bucket_idx]
+            .items
+            .iter()
+            .copied()
+            // SAFETY: Nodes referenced by a bucket are owned by the map and
+            // stay valid until they are removed from the bucket.
+            .find(|node| unsafe { node.as_ref() }.key == key)
+        {
+            self.touch(node);
+            // SAFETY: The node is valid and we are mutably borrowing the map.
+            let node = unsafe { node.as_mut() };
+            return Some(std::mem::replace(&mut node.value, value));
         }
-        bucket.items.push((key, value));
-        self.entries_count += 1;
+        self.push_entry(bucket_idx, key, value);
+        self.evict();
         None
     }
 
     /// Returns a reference to the value corresponding to the key.
     ///
-    /// The key may be any borrowed form of the map’s key type, but Hash and Eq 
+    /// The key may be any borrowed form of the map’s key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
     ///
+    /// This does not count as an access in access order, use [`get_refresh`]
+    /// to move the entry to the back of the map.
+    ///
+    /// [`get_refresh`]: LinkedHashMap::get_refresh
+    ///
     /// # Examples
     ///
     /// ```
@@ -314,23 +702,93 @@ where
     /// assert_eq!(map.get(&1), Some(&"a"));
     /// assert_eq!(map.get(&2), None);
     /// ```
+    #[allow(unsafe_code)]
     pub fn get<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        let idx = self.index(key);
-        self.buckets[idx]
-            .items
-            .iter()
-            .find(|(k, _)| k.borrow() == key)
-            .map(|(_, v)| v)
+        let node = self.find(key)?;
+        // SAFETY: The node was found in a bucket so it is still valid, and it
+        // cannot be removed while the map is borrowed.
+        Some(unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// Returns a reference to the value corresponding to the key, counting the
+    /// lookup as an access.
+    ///
+    /// The key may be any borrowed form of the map’s key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// If the map is in access order and the key is present, its entry is
+    /// moved to the back of the map. Otherwise, this is the same as [`get`].
+    ///
+    /// [`get`]: LinkedHashMap::get
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::with_access_order();
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// assert_eq!(map.get_refresh(&1), Some(&"a"));
+    /// assert_eq!(map.get_refresh(&3), None);
+    ///
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&2, &1]);
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn get_refresh<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = self.find(key)?;
+        self.touch(node);
+        // SAFETY: The node was found in a bucket so it is still valid, and it
+        // cannot be removed while the map is borrowed.
+        Some(unsafe { &(*node.as_ptr()).value })
     }
 
-    /// Removes a key from the map, returning the value at the key if the key 
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map’s key type, but Hash and Eq
+    /// on the borrowed form must match those for the key type.
+    ///
+    /// If the map is in access order and the key is present, its entry is
+    /// moved to the back of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::new();
+    /// map.insert(1, "a");
+    /// if let Some(x) = map.get_mut(&1) {
+    ///     *x = "b";
+    /// }
+    /// assert_eq!(map[&1], "b");
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = self.find(key)?;
+        self.touch(node);
+        // SAFETY: The node was found in a bucket so it is still valid, and it
+        // cannot be removed while the map is mutably borrowed.
+        Some(unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
     /// was previously in the map.
     ///
-    /// The key may be any borrowed form of the map’s key type, but Hash and Eq 
+    /// The key may be any borrowed form of the map’s key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
@@ -343,31 +801,134 @@ where
     /// assert_eq!(map.remove(&1), Some("a"));
     /// assert_eq!(map.remove(&1), None);
     /// ```
+    #[allow(unsafe_code)]
     pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        let idx = self.index(key);
-        let bucket = &mut self.buckets[idx];
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let bucket_idx = self.index(key);
+        let bucket = &mut self.buckets[bucket_idx];
 
         let entry_idx = bucket
             .items
             .iter()
-            .position(|(k, _)| k.borrow() == key)?;
+            // SAFETY: Nodes referenced by a bucket are owned by the map and
+            // stay valid until they are removed from the bucket.
+            .position(|node| unsafe { node.as_ref() }.key.borrow() == key)?;
+        Some(self.remove_entry_at(bucket_idx, entry_idx).1)
+    }
+
+    /// Removes the entry at the front of the map and returns it, or `None` if
+    /// the map is empty.
+    ///
+    /// The front entry is the one inserted first, or the least recently used
+    /// one if the map is in access order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map = LinkedHashMap::new();
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// assert_eq!(map.pop_front(), Some((1, "a")));
+    /// assert_eq!(map.pop_front(), Some((2, "b")));
+    /// assert_eq!(map.pop_front(), None);
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        let head = self.head?;
+        // SAFETY: Head is not None so we know the raw pointer inside is still
+        // valid.
+        let bucket_idx = self.index(&unsafe { head.as_ref() }.key);
+        let entry_idx = self.buckets[bucket_idx]
+            .items
+            .iter()
+            .position(|&node| node == head)
+            .expect("Entry must be in the bucket of its key");
+        Some(self.remove_entry_at(bucket_idx, entry_idx))
+    }
+
+    /// Sets the maximum number of entries the map holds, evicting entries
+    /// from the front of the map until it fits. `None` removes the limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is `Some(0)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::LinkedHashMap;
+    ///
+    /// let mut map: LinkedHashMap<_, _> = (0..10).map(|i| (i, i)).collect();
+    /// map.set_capacity_limit(Some(3));
+    ///
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&7, &8, &9]);
+    /// ```
+    pub fn set_capacity_limit(&mut self, limit: Option<usize>) {
+        assert!(
+            limit != Some(0),
+            "Capacity limit must be greater than zero"
+        );
+        self.capacity_limit = limit;
+        self.evict();
+    }
+
+    /// Remove entries from the front of the map until it is within its
+    /// capacity limit, handing them to the eviction callback.
+    fn evict(&mut self) {
+        let limit = match self.capacity_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        while self.entries_count > limit {
+            let (key, value) = match self.pop_front() {
+                Some(entry) => entry,
+                None => break,
+            };
+            if let Some(on_evict) = self.on_evict.as_mut() {
+                on_evict(key, value);
+            }
+        }
+    }
+
+    /// Remove the entry at `entry_idx` in the bucket at `bucket_idx` from the
+    /// map and return its key and value.
+    #[allow(unsafe_code)]
+    fn remove_entry_at(
+        &mut self,
+        bucket_idx: usize,
+        entry_idx: usize,
+    ) -> (K, V) {
+        let node = self.buckets[bucket_idx].items.swap_remove(entry_idx);
+        self.unlink(node);
         self.entries_count -= 1;
-        Some(bucket.items.swap_remove(entry_idx).1)
+
+        // SAFETY: The node has been detached from its bucket and from the
+        // chain of entries, so this is the last pointer to it.
+        let node = unsafe { Box::from_raw(node.as_ptr()) };
+        (node.key, node.value)
     }
 
-    /// Gets the given key’s corresponding entry in the map for in-place 
+    /// Gets the given key’s corresponding entry in the map for in-place
     /// manipulation.
     ///
+    /// If the map is in access order and the key is present, its entry is
+    /// moved to the back of the map.
+    ///
     /// # Examples
     ///
     /// ```
-    /// use std::collections::HashMap;
+    /// use rusty_crust::collections::LinkedHashMap;
     ///
-    /// let mut letters = HashMap::new();
+    /// let mut letters = LinkedHashMap::new();
     ///
     /// for ch in "a short treatise on fungi".chars() {
     ///     let counter = letters.entry(ch).or_insert(0);
@@ -379,6 +940,7 @@ where
     /// assert_eq!(letters[&'u'], 1);
     /// assert_eq!(letters.get(&'y'), None);
     /// ```
+    #[allow(unsafe_code)]
     pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
         if self.buckets.is_empty()
             || self.entries_count > 3 * self.buckets.len() / 4
@@ -387,18 +949,22 @@ where
         }
 
         let bucket_idx = self.index(&key);
-        if let Some(entry_idx) = self.buckets[bucket_idx]
+        if let Some(node) = self.buckets[bucket_idx]
             .items
-            .iter_mut()
-            .position(|&mut (ref k, _)| *k == key)
+            .iter()
+            .copied()
+            // SAFETY: Nodes referenced by a bucket are owned by the map and
+            // stay valid until they are removed from the bucket.
+            .find(|node| unsafe { node.as_ref() }.key == key)
         {
-            // We are using `position` instead of `find` cause `self.buckets` 
-            // will be borrowed inside the scope of the if statement; using 
-            // `find` somehow scoped `self.buckets` within the function, 
-            // disallowing `self` to be re-borrowed in the else case.
-            let &mut (ref key, ref mut value) =
-                &mut self.buckets[bucket_idx].items[entry_idx];
-            return Entry::Occupied(OccupiedEntry { key, value });
+            self.touch(node);
+            // SAFETY: The node is valid and the returned entry mutably
+            // borrows the map, so the node cannot be removed in the meantime.
+            let node = unsafe { &mut *node.as_ptr() };
+            return Entry::Occupied(OccupiedEntry {
+                key: &node.key,
+                value: &mut node.value,
+            });
         }
         Entry::Vacant(VacantEntry {
             key,
@@ -409,7 +975,7 @@ where
 
     /// Returns true if the map contains a value for the specified key.
     ///
-    /// The key may be any borrowed form of the map’s key type, but Hash and Eq 
+    /// The key may be any borrowed form of the map’s key type, but Hash and Eq
     /// on the borrowed form must match those for the key type.
     ///
     /// # Examples
@@ -427,16 +993,16 @@ where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        let idx = self.index(key);
-        self.buckets[idx]
-            .items
-            .iter()
-            .any(|(k, _)| k.borrow() == key)
+        self.find(key).is_some()
     }
 
-    /// Increase the size of the array of buckets. If there is no bucket, extend 
-    /// the array by one, otherwise, double the array's size and reindex all 
+    /// Increase the size of the array of buckets. If there is no bucket, extend
+    /// the array by one, otherwise, double the array's size and reindex all
     /// existing entries.
+    ///
+    /// Only the pointers held by the buckets are moved, the chain of entries
+    /// is left as is.
+    #[allow(unsafe_code)]
     fn grow(&mut self) {
         let target_size = match self.buckets.len() {
             0 => 1,
@@ -445,21 +1011,44 @@ where
 
         let mut buckets = Vec::with_capacity(target_size);
         buckets.extend((0..target_size).map(|_| Bucket::default()));
-        for (key, value) in self
+        for node in self
             .buckets
             .iter_mut()
             .flat_map(|bucket| bucket.items.drain(..))
         {
+            // SAFETY: Nodes referenced by a bucket are owned by the map and
+            // stay valid until they are removed from the bucket.
+            let key = unsafe { &node.as_ref().key };
             let idx = derive_bucket_index(
                 self.hasher_builder.build_hasher(),
-                &key,
+                key,
                 target_size,
             );
-            buckets[idx].items.push((key, value));
+            buckets[idx].items.push(node);
         }
         self.buckets = buckets;
     }
 
+    /// Get the node holding `key`, if there is one.
+    #[allow(unsafe_code)]
+    fn find<Q>(&self, key: &Q) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let idx = self.index(key);
+        self.buckets[idx]
+            .items
+            .iter()
+            .copied()
+            // SAFETY: Nodes referenced by a bucket are owned by the map and
+            // stay valid until they are removed from the bucket.
+            .find(|node| unsafe { node.as_ref() }.key.borrow() == key)
+    }
+
     /// Get the index of the bucket for `key`.
     fn index<Q>(&self, key: &Q) -> usize
     where
@@ -503,72 +1092,125 @@ where
     }
 }
 
-/// An iterator over the elements of a [`LinkedHashMap`].
+/// An iterator over the elements of a [`LinkedHashMap`] in insertion order.
 ///
 /// [`LinkedHashMap`]: crate::collections::LinkedHashMap
 #[derive(Debug)]
-pub struct Iter<'a, K, V, S> {
-    map: &'a LinkedHashMap<K, V, S>,
-    bucket_idx: usize,
-    bucket_entry_idx: usize,
+pub struct Iter<'a, K, V> {
+    it: Option<NonNull<Node<K, V>>>,
+    len: usize,
+    marker: PhantomData<&'a Node<K, V>>,
 }
 
-impl<'a, K, V, S> Iterator for Iter<'a, K, V, S> {
+// SAFETY: The iterator behaves like a `&LinkedHashMap<K, V, S>`, it only gives
+// out shared references to the keys and values.
+unsafe impl<K: Sync, V: Sync> Send for Iter<'_, K, V> {}
+
+// SAFETY: Same as above.
+unsafe impl<K: Sync, V: Sync> Sync for Iter<'_, K, V> {}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
     type Item = (&'a K, &'a V);
 
-    /// We keep two indices, one index for the bucket and one index for the 
-    /// entry within the bucket that is currently pointed at.
+    /// We follow the chain of entries starting from the one at the front.
+    #[allow(unsafe_code)]
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(bucket) = self.map.buckets.get(self.bucket_idx) {
-            if let Some((key, value)) =
-                bucket.items.get(self.bucket_entry_idx)
-            {
-                self.bucket_entry_idx += 1;
-                return Some((key, value));
-            }
-            self.bucket_idx += 1;
-            self.bucket_entry_idx = 0;
+        if self.len == 0 {
+            return None;
         }
-        None
+        // SAFETY: Current Node is Some, so we know its raw pointer is still
+        // valid
+        self.it.map(|node| unsafe {
+            let node = &*node.as_ptr();
+            self.it = node.next;
+            self.len -= 1;
+            (&node.key, &node.value)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
     }
 }
 
 impl<'a, K, V, S> IntoIterator for &'a LinkedHashMap<K, V, S> {
     type Item = (&'a K, &'a V);
 
-    type IntoIter = Iter<'a, K, V, S>;
+    type IntoIter = Iter<'a, K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
-        Self::IntoIter {
-            map: self,
-            bucket_idx: 0,
-            bucket_entry_idx: 0,
-        }
+        self.iter()
+    }
+}
+
+/// An iterator over the keys of a [`LinkedHashMap`] in insertion order.
+///
+/// [`LinkedHashMap`]: crate::collections::LinkedHashMap
+#[derive(Debug)]
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
     }
 }
 
-/// An iterator over the elements of a [`LinkedHashMap`].
+/// An iterator over the values of a [`LinkedHashMap`] in insertion order.
+///
+/// [`LinkedHashMap`]: crate::collections::LinkedHashMap
+#[derive(Debug)]
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An owning iterator over the elements of a [`LinkedHashMap`] in insertion
+/// order.
 ///
 /// [`LinkedHashMap`]: crate::collections::LinkedHashMap
 #[derive(Debug)]
 pub struct IntoIter<K, V, S> {
     map: LinkedHashMap<K, V, S>,
-    bucket_idx: usize,
 }
 
 impl<K, V, S> Iterator for IntoIter<K, V, S> {
     type Item = (K, V);
 
-    /// We keep two indices, one index for the bucket and one index for the 
-    /// entry within the bucket that is currently pointed at.
+    /// We detach the entry at the front of the chain and take ownership of
+    /// its key and value.
+    #[allow(unsafe_code)]
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(bucket) = self.map.buckets.get_mut(self.bucket_idx) {
-            if let Some((key, value)) = bucket.items.pop() {
-                return Some((key, value));
-            }
-            self.bucket_idx += 1;
-        }
-        None
+        let head = self.map.head?;
+        self.map.unlink(head);
+        self.map.entries_count -= 1;
+        // SAFETY: The buckets were emptied when the iterator was created, so
+        // after being unlinked from the chain, there is no other pointer to
+        // this node.
+        let node = unsafe { Box::from_raw(head.as_ptr()) };
+        Some((node.key, node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.map.entries_count, Some(self.map.entries_count))
     }
 }
 
@@ -577,11 +1219,11 @@ impl<K, V, S> IntoIterator for LinkedHashMap<K, V, S> {
 
     type IntoIter = IntoIter<K, V, S>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        Self::IntoIter {
-            map: self,
-            bucket_idx: 0,
-        }
+    fn into_iter(mut self) -> Self::IntoIter {
+        // The entries are consumed by following the chain, the buckets are
+        // not needed anymore.
+        self.buckets = Vec::new();
+        Self::IntoIter { map: self }
     }
 }
 
@@ -598,6 +1240,26 @@ pub struct VacantEntry<'a, K, V, S> {
     map: &'a mut LinkedHashMap<K, V, S>,
 }
 
+impl<'a, K, V, S> VacantEntry<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Sets the value of the entry with the `VacantEntry`'s key, placing it
+    /// after every other entry in the iteration order, and returns a mutable
+    /// reference to it.
+    #[allow(unsafe_code)]
+    pub fn insert(self, value: V) -> &'a mut V {
+        let node = self.map.push_entry(self.bucket_idx, self.key, value);
+        // The new entry is at the back and the capacity limit is at least one,
+        // so the eviction never removes it.
+        self.map.evict();
+        // SAFETY: The node was just allocated and is owned by the map, which
+        // stays mutably borrowed for `'a`.
+        unsafe { &mut (*node.as_ptr()).value }
+    }
+}
+
 #[derive(Debug)]
 pub enum Entry<'a, K, V, S> {
     Occupied(OccupiedEntry<'a, K, V>),
@@ -643,17 +1305,7 @@ where
     pub fn or_insert(self, value: V) -> &'a mut V {
         match self {
             Self::Occupied(OccupiedEntry { key: _, value }) => value,
-            Self::Vacant(VacantEntry {
-                key,
-                bucket_idx,
-                map,
-            }) => {
-                map.insert(key, value);
-                // unwrap() cause we just inserted a value
-                let &mut (_, ref mut value) =
-                    map.buckets[bucket_idx].items.last_mut().unwrap();
-                value
-            }
+            Self::Vacant(vacant_entry) => vacant_entry.insert(value),
         }
     }
 
@@ -670,17 +1322,9 @@ where
     {
         match self {
             Self::Occupied(OccupiedEntry { key: _, value }) => value,
-            Self::Vacant(VacantEntry {
-                key,
-                bucket_idx,
-                map,
-            }) => {
-                let value = f(&key);
-                map.insert(key, value);
-                // unwrap() cause we just inserted a value
-                let &mut (_, ref mut value) =
-                    map.buckets[bucket_idx].items.last_mut().unwrap();
-                value
+            Self::Vacant(vacant_entry) => {
+                let value = f(&vacant_entry.key);
+                vacant_entry.insert(value)
             }
         }
     }
@@ -766,5 +1410,196 @@ mod tests {
         // Check if the iterator has gone through all items.
         assert!(has_seen.iter().all(|(_, &v)| v));
     }
-}
 
+    #[test]
+    fn iteration_follows_insertion_order() {
+        let mut map = LinkedHashMap::new();
+        // Enough entries to go through a few resizes of the buckets.
+        for i in 0..100 {
+            map.insert(i, i * 10);
+        }
+        assert!(map.keys().copied().eq(0..100));
+        assert!(map.values().copied().eq((0..100).map(|i| i * 10)));
+
+        // Updating an existing key does not move its entry.
+        assert_eq!(map.insert(0, 42), Some(0));
+        assert_eq!(map.iter().next(), Some((&0, &42)));
+
+        // Removing entries keeps the relative order of the remaining ones.
+        for i in (0..100).step_by(2) {
+            assert!(map.remove(&i).is_some());
+        }
+        assert_eq!(map.len(), 50);
+        assert!(map.keys().copied().eq((1..100).step_by(2)));
+
+        // Re-inserting a removed key places it at the back.
+        map.insert(0, 0);
+        assert_eq!(map.keys().last(), Some(&0));
+
+        let keys: Vec<_> = map.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys.len(), 51);
+        assert!(keys[..50].iter().copied().eq((1..100).step_by(2)));
+        assert_eq!(keys[50], 0);
+    }
+
+    #[test]
+    fn entry_inserts_at_the_back() {
+        let mut map = LinkedHashMap::new();
+        for ch in "linked hash map".chars() {
+            *map.entry(ch).or_insert(0) += 1;
+        }
+        let keys: String = map.keys().collect();
+        assert_eq!(keys, "linked hasmp");
+        assert_eq!(map[&' '], 2);
+        assert_eq!(map[&'h'], 2);
+    }
+
+    #[test]
+    fn operations_on_empty_map() {
+        let mut map: LinkedHashMap<&str, i32> = LinkedHashMap::new();
+        assert_eq!(map.get("foo"), None);
+        assert!(!map.contains_key("foo"));
+        assert_eq!(map.remove("foo"), None);
+        assert_eq!(map.iter().next(), None);
+        assert_eq!(format!("{:?}", map), "{}");
+    }
+
+    #[test]
+    fn access_order_moves_touched_entries_to_the_back() {
+        let mut map = LinkedHashMap::with_access_order();
+        assert!(map.is_access_order());
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+
+        assert_eq!(map.get_refresh(&0), Some(&0));
+        assert!(map.keys().copied().eq([1, 2, 3, 4, 0]));
+
+        *map.get_mut(&2).unwrap() += 10;
+        assert!(map.keys().copied().eq([1, 3, 4, 0, 2]));
+
+        *map.entry(1).or_insert(0) += 10;
+        assert!(map.keys().copied().eq([3, 4, 0, 2, 1]));
+
+        map.insert(3, 3);
+        assert!(map.keys().copied().eq([4, 0, 2, 1, 3]));
+
+        // Misses, shared lookups and iteration do not change the order.
+        assert_eq!(map.get_refresh(&42), None);
+        map.get_mut(&42);
+        map.get(&4);
+        assert!(map.contains_key(&0));
+        map.iter().for_each(drop);
+        assert!(map.keys().copied().eq([4, 0, 2, 1, 3]));
+        assert!(map.values().copied().eq([4, 0, 12, 11, 3]));
+
+        map.set_access_order(false);
+        assert_eq!(map.get_refresh(&4), Some(&4));
+        map.get_mut(&4);
+        assert!(map.keys().copied().eq([4, 0, 2, 1, 3]));
+    }
+
+    #[test]
+    fn lookups_while_iterating_in_access_order() {
+        let mut map = LinkedHashMap::with_access_order();
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+
+        map.get_refresh(&1);
+        map.get_refresh(&3);
+
+        // Looking up every entry while iterating yields each entry once, in
+        // the order of the last accesses.
+        let mut seen = Vec::new();
+        for (k, v) in &map {
+            assert_eq!(map.get(k), Some(v));
+            seen.push(*k);
+        }
+        assert_eq!(seen, [0, 2, 4, 1, 3]);
+        assert!(map.keys().copied().eq([0, 2, 4, 1, 3]));
+    }
+
+    #[test]
+    fn auto_traits_follow_the_entries() {
+        fn is_send<T: Send>() {}
+        fn is_sync<T: Sync>() {}
+        is_send::<LinkedHashMap<i32, String>>();
+        is_sync::<LinkedHashMap<i32, String>>();
+        // Send but not Sync, moving the map moves the entries along.
+        is_send::<LinkedHashMap<i32, std::cell::Cell<i32>>>();
+        is_send::<Iter<'_, i32, String>>();
+        is_sync::<Iter<'_, i32, String>>();
+        is_send::<Keys<'_, i32, String>>();
+        is_sync::<Keys<'_, i32, String>>();
+        is_send::<Values<'_, i32, String>>();
+        is_sync::<Values<'_, i32, String>>();
+
+        let mut map = LinkedHashMap::with_access_order();
+        map.insert(1, "a");
+        map.insert(2, "b");
+        std::thread::scope(|s| {
+            s.spawn(|| assert_eq!(map.get(&1), Some(&"a")));
+            s.spawn(|| assert_eq!(map.get(&2), Some(&"b")));
+        });
+        let mut iter = map.iter();
+        iter.next();
+        let rest: Vec<_> = std::thread::scope(|s| {
+            s.spawn(move || iter.collect()).join().unwrap()
+        });
+        assert_eq!(rest, [(&2, &"b")]);
+        assert!(map.keys().copied().eq([1, 2]));
+    }
+
+    #[test]
+    fn capacity_limit_evicts_least_recently_used() {
+        use std::sync::{Arc, Mutex};
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut map = LinkedHashMap::with_capacity_limit(3);
+        map.set_access_order(true);
+        map.set_eviction_callback({
+            let evicted = Arc::clone(&evicted);
+            move |k, v| evicted.lock().unwrap().push((k, v))
+        });
+        assert_eq!(map.capacity_limit(), Some(3));
+
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", 3);
+        map.get_refresh("a");
+        map.insert("d", 4);
+        *map.entry("e").or_insert(0) += 5;
+
+        assert_eq!(map.len(), 3);
+        assert!(map.keys().copied().eq(["a", "d", "e"]));
+        assert_eq!(*evicted.lock().unwrap(), [("b", 2), ("c", 3)]);
+
+        // Explicit removals are not evictions.
+        assert_eq!(map.remove("a"), Some(1));
+        assert_eq!(map.pop_front(), Some(("d", 4)));
+        assert_eq!(evicted.lock().unwrap().len(), 2);
+
+        map.insert("f", 6);
+        map.insert("g", 7);
+        map.set_capacity_limit(Some(1));
+        assert!(map.keys().copied().eq(["g"]));
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            [("b", 2), ("c", 3), ("e", 5), ("f", 6)]
+        );
+
+        map.set_capacity_limit(None);
+        for i in 0..10 {
+            map.insert("h", i);
+            map.insert(["i", "j", "k"][i % 3], i);
+        }
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn capacity_limit_must_be_positive() {
+        LinkedHashMap::<i32, i32>::with_capacity_limit(0);
+    }
+}
@@ -0,0 +1,1052 @@
+//! A doubly-linked list whose nodes are allocated from a slab, along with its
+//! iterators.
+
+use std::{fmt, iter::FusedIterator, marker::PhantomData};
+
+/// The index of a slot in the slab.
+type Index = u32;
+
+/// Stands for a missing node, like a null pointer.
+const NIL: Index = Index::MAX;
+
+/// The number of bits of an index that give the offset of its slot in a chunk.
+const CHUNK_BITS: u32 = 6;
+
+/// The number of slots in a chunk.
+const CHUNK_LEN: usize = 1 << CHUNK_BITS;
+
+/// The number of chunks after which the indices would reach `NIL`.
+const MAX_CHUNKS: usize = NIL as usize >> CHUNK_BITS;
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    prev: Index,
+    next: Index,
+    data: T,
+}
+
+#[derive(Debug, Clone)]
+enum Slot<T> {
+    Occupied(Node<T>),
+    /// A free slot, which links to the next free slot.
+    Vacant(Index),
+}
+
+/// A doubly-linked list whose nodes are allocated from a slab.
+///
+/// The SlabList is a double-ended queue like [`DoublyLinkedList`]: it can
+/// push, pop, and peek at both ends, and be iterated from both ends. It does
+/// not have the cursors, splitting, or sorting of [`DoublyLinkedList`] though.
+///
+/// Instead of being boxed one by one, the nodes live in a slab made of chunks
+/// of contiguous slots, and link to each other through indices instead of
+/// pointers. Popping an element frees its slot onto an internal free list,
+/// from which the next push takes it, so a list whose length stays bounded
+/// stops allocating altogether. Traversal also makes better use of the CPU
+/// cache than with individually boxed nodes.
+///
+/// When no slot is free, the slab grows by one chunk. The existing chunks are
+/// never moved, so the nodes stay in place and a push costs at most one chunk
+/// allocation. The slab never shrinks, so a list that was once long keeps its
+/// capacity until it is dropped, even after [`clear`] is called.
+///
+/// [`DoublyLinkedList`]: crate::collections::DoublyLinkedList
+/// [`clear`]: SlabList::clear
+///
+/// # Examples
+///
+/// ```
+/// use rusty_crust::collections::SlabList;
+///
+/// let mut list = SlabList::with_capacity(2);
+/// let capacity = list.capacity();
+/// list.push_back(1);
+/// list.push_back(2);
+/// assert_eq!(list.pop_front(), Some(1));
+///
+/// // The slot of the popped element is reused.
+/// list.push_front(0);
+/// assert_eq!(list.capacity(), capacity);
+/// assert_eq!(list.iter().collect::<Vec<_>>(), [&0, &2]);
+/// ```
+#[derive(Clone)]
+pub struct SlabList<T> {
+    chunks: Vec<Box<[Slot<T>]>>,
+    /// The first free slot, from which the free list starts.
+    free: Index,
+    head: Index,
+    tail: Index,
+    len: usize,
+}
+
+impl<T> Default for SlabList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SlabList<T> {
+    /// Creates an empty SlabList.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let list: SlabList<u32> = SlabList::new();
+    /// ```
+    pub const fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            free: NIL,
+            head: NIL,
+            tail: NIL,
+            len: 0,
+        }
+    }
+
+    /// Creates an empty SlabList with room for at least `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity overflows the slab indices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let list: SlabList<u32> = SlabList::with_capacity(10);
+    /// assert!(list.capacity() >= 10);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut list = Self::new();
+        list.reserve(capacity);
+        list
+    }
+
+    /// Returns the number of elements the list can hold without allocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::with_capacity(10);
+    /// list.push_back(1);
+    /// assert!(list.capacity() >= 10);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.chunks.len() * CHUNK_LEN
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows the slab indices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// list.push_back(1);
+    /// list.reserve(10);
+    /// assert!(list.capacity() >= 11);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        let wanted = self
+            .len
+            .checked_add(additional)
+            .expect("SlabList capacity overflow");
+        // The free slots are used before growing the slab.
+        if wanted > self.capacity() {
+            self.grow(wanted.div_ceil(CHUNK_LEN) - self.chunks.len());
+        }
+    }
+
+    /// Returns true if the SlabList is empty.
+    ///
+    /// This operation should compute in O(1) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// assert!(list.is_empty());
+    ///
+    /// list.push_front("foo");
+    /// assert!(!list.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the length of the SlabList.
+    ///
+    /// This operation should compute in O(1) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// list.push_front(2);
+    /// list.push_back(3);
+    /// assert_eq!(list.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Removes all elements from the SlabList, keeping its capacity.
+    ///
+    /// This operation should compute in O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::with_capacity(2);
+    /// list.push_front(2);
+    /// list.push_front(1);
+    ///
+    /// list.clear();
+    /// assert!(list.is_empty());
+    /// assert!(list.capacity() >= 2);
+    /// ```
+    pub fn clear(&mut self) {
+        while self.pop_back().is_some() {}
+    }
+
+    /// Returns true if the SlabList contains an element equal to the given
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let list: SlabList<_> = (0..3).collect();
+    /// assert!(list.contains(&0));
+    /// assert!(!list.contains(&10));
+    /// ```
+    pub fn contains(&self, data: &T) -> bool
+    where
+        T: PartialEq<T>,
+    {
+        self.iter().any(|it| it == data)
+    }
+
+    /// Provides a reference to the front element, or None if the list is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// assert_eq!(list.front(), None);
+    ///
+    /// list.push_front(1);
+    /// assert_eq!(list.front(), Some(&1));
+    /// ```
+    pub fn front(&self) -> Option<&T> {
+        self.get_node(self.head).map(|node| &node.data)
+    }
+
+    /// Provides a mutable reference to the front element, or None if the list
+    /// is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// list.push_front(1);
+    /// if let Some(x) = list.front_mut() {
+    ///     *x = 5;
+    /// }
+    /// assert_eq!(list.front(), Some(&5));
+    /// ```
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        let head = self.head;
+        self.get_node_mut(head).map(|node| &mut node.data)
+    }
+
+    /// Provides a reference to the back element, or None if the list is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// assert_eq!(list.back(), None);
+    ///
+    /// list.push_back(1);
+    /// assert_eq!(list.back(), Some(&1));
+    /// ```
+    pub fn back(&self) -> Option<&T> {
+        self.get_node(self.tail).map(|node| &node.data)
+    }
+
+    /// Provides a mutable reference to the back element, or None if the list
+    /// is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// list.push_back(1);
+    /// if let Some(x) = list.back_mut() {
+    ///     *x = 5;
+    /// }
+    /// assert_eq!(list.back(), Some(&5));
+    /// ```
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        let tail = self.tail;
+        self.get_node_mut(tail).map(|node| &mut node.data)
+    }
+
+    /// Adds an element first in the list.
+    ///
+    /// This operation should compute in amortized O(1) time, and does not
+    /// allocate when a slot is free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slab is full and growing it overflows the slab indices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// list.push_front(2);
+    /// list.push_front(1);
+    /// assert_eq!(list.front(), Some(&1));
+    /// ```
+    pub fn push_front(&mut self, data: T) {
+        let head = self.head;
+        let index = self.alloc(Node {
+            prev: NIL,
+            next: head,
+            data,
+        });
+        match self.get_node_mut(head) {
+            None => self.tail = index,
+            Some(node) => node.prev = index,
+        }
+        self.head = index;
+    }
+
+    /// Removes the first element and returns it, or None if the list is empty.
+    ///
+    /// This operation should compute in O(1) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// assert_eq!(list.pop_front(), None);
+    ///
+    /// list.push_front(1);
+    /// list.push_front(3);
+    /// assert_eq!(list.pop_front(), Some(3));
+    /// assert_eq!(list.pop_front(), Some(1));
+    /// assert_eq!(list.pop_front(), None);
+    /// ```
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.head == NIL {
+            return None;
+        }
+        let node = self.dealloc(self.head);
+        self.head = node.next;
+        match self.get_node_mut(node.next) {
+            None => self.tail = NIL,
+            Some(next) => next.prev = NIL,
+        }
+        Some(node.data)
+    }
+
+    /// Appends an element to the back of a list.
+    ///
+    /// This operation should compute in amortized O(1) time, and does not
+    /// allocate when a slot is free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slab is full and growing it overflows the slab indices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// list.push_back(1);
+    /// list.push_back(3);
+    /// assert_eq!(list.back(), Some(&3));
+    /// ```
+    pub fn push_back(&mut self, data: T) {
+        let tail = self.tail;
+        let index = self.alloc(Node {
+            prev: tail,
+            next: NIL,
+            data,
+        });
+        match self.get_node_mut(tail) {
+            None => self.head = index,
+            Some(node) => node.next = index,
+        }
+        self.tail = index;
+    }
+
+    /// Removes the last element from a list and returns it, or None if it is
+    /// empty.
+    ///
+    /// This operation should compute in O(1) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list = SlabList::new();
+    /// assert_eq!(list.pop_back(), None);
+    /// list.push_back(1);
+    /// list.push_back(3);
+    /// assert_eq!(list.pop_back(), Some(3));
+    /// ```
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.tail == NIL {
+            return None;
+        }
+        let node = self.dealloc(self.tail);
+        self.tail = node.prev;
+        match self.get_node_mut(node.prev) {
+            None => self.head = NIL,
+            Some(prev) => prev.next = NIL,
+        }
+        Some(node.data)
+    }
+
+    /// Provides an iterator that can be walked from both ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let list: SlabList<_> = (0..3).collect();
+    ///
+    /// let mut iter = list.iter();
+    /// assert_eq!(iter.next(), Some(&0));
+    /// assert_eq!(iter.next_back(), Some(&2));
+    /// assert_eq!(iter.next(), Some(&1));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            chunks: &self.chunks,
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+        }
+    }
+
+    /// Provides an iterator with mutable references that can be walked from
+    /// both ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rusty_crust::collections::SlabList;
+    ///
+    /// let mut list: SlabList<_> = (0..3).collect();
+    /// for element in list.iter_mut() {
+    ///     *element += 10;
+    /// }
+    /// assert_eq!(list.iter().collect::<Vec<_>>(), [&10, &11, &12]);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            chunks: self.chunks.as_mut_ptr(),
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+
+    /// Appends `additional` chunks to the slab, and puts their slots at the
+    /// front of the free list, lowest index first.
+    fn grow(&mut self, additional: usize) {
+        if additional == 0 {
+            return;
+        }
+        let n_chunks = self.chunks.len() + additional;
+        assert!(n_chunks <= MAX_CHUNKS, "SlabList capacity overflow");
+        let (start, end) = (self.capacity(), n_chunks * CHUNK_LEN);
+        self.chunks.reserve_exact(additional);
+        for chunk_start in (start..end).step_by(CHUNK_LEN) {
+            let chunk = (chunk_start + 1..chunk_start + CHUNK_LEN + 1)
+                .map(|next| {
+                    Slot::Vacant(if next == end {
+                        self.free
+                    } else {
+                        next as Index
+                    })
+                })
+                .collect();
+            self.chunks.push(chunk);
+        }
+        self.free = start as Index;
+    }
+
+    /// Puts the node in a free slot, growing the slab if there is none, and
+    /// returns its index.
+    fn alloc(&mut self, node: Node<T>) -> Index {
+        if self.free == NIL {
+            self.grow(1);
+        }
+        let index = self.free;
+        let (chunk, offset) = split(index);
+        match std::mem::replace(&mut self.chunks[chunk][offset], Slot::Occupied(node)) {
+            Slot::Vacant(next_free) => self.free = next_free,
+            Slot::Occupied(_) => unreachable!("free list links to an occupied slot"),
+        }
+        self.len += 1;
+        index
+    }
+
+    /// Takes the node out of its slot, which is put on the free list.
+    fn dealloc(&mut self, index: Index) -> Node<T> {
+        let (chunk, offset) = split(index);
+        let slot = std::mem::replace(&mut self.chunks[chunk][offset], Slot::Vacant(self.free));
+        self.free = index;
+        self.len -= 1;
+        match slot {
+            Slot::Occupied(node) => node,
+            Slot::Vacant(_) => unreachable!("list links to a free slot"),
+        }
+    }
+
+    fn get_node(&self, index: Index) -> Option<&Node<T>> {
+        node(&self.chunks, index)
+    }
+
+    fn get_node_mut(&mut self, index: Index) -> Option<&mut Node<T>> {
+        if index == NIL {
+            return None;
+        }
+        let (chunk, offset) = split(index);
+        match &mut self.chunks[chunk][offset] {
+            Slot::Occupied(node) => Some(node),
+            Slot::Vacant(_) => unreachable!("list links to a free slot"),
+        }
+    }
+}
+
+/// Splits an index into the index of its chunk and the offset of its slot in
+/// that chunk.
+fn split(index: Index) -> (usize, usize) {
+    let index = index as usize;
+    (index >> CHUNK_BITS, index & (CHUNK_LEN - 1))
+}
+
+/// Returns the node at the given index, or None if the index is `NIL`.
+fn node<T>(chunks: &[Box<[Slot<T>]>], index: Index) -> Option<&Node<T>> {
+    if index == NIL {
+        return None;
+    }
+    let (chunk, offset) = split(index);
+    match &chunks[chunk][offset] {
+        Slot::Occupied(node) => Some(node),
+        Slot::Vacant(_) => unreachable!("list links to a free slot"),
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SlabList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for SlabList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for SlabList<T> {}
+
+impl<T> FromIterator<T> for SlabList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for SlabList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        iter.for_each(|data| self.push_back(data));
+    }
+}
+
+/// An iterator over the elements of a SlabList.
+///
+/// This struct is created by [`SlabList::iter()`]. See its documentation for
+/// more.
+///
+/// [`SlabList::iter()`]: crate::collections::SlabList#iter;
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    chunks: &'a [Box<[Slot<T>]>],
+    head: Index,
+    tail: Index,
+    len: usize,
+}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = node(self.chunks, self.head)?;
+        self.len -= 1;
+        self.head = node.next;
+        Some(&node.data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = node(self.chunks, self.tail)?;
+        self.len -= 1;
+        self.tail = node.prev;
+        Some(&node.data)
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// A mutable iterator over the elements of a SlabList.
+///
+/// This struct is created by [`SlabList::iter_mut()`]. See its documentation
+/// for more.
+///
+/// [`SlabList::iter_mut()`]: crate::collections::SlabList#iter_mut;
+#[derive(Debug)]
+pub struct IterMut<'a, T> {
+    // A raw pointer is needed because the references that are given out point
+    // into the same chunks, which can not be borrowed mutably more than once.
+    chunks: *mut Box<[Slot<T>]>,
+    head: Index,
+    tail: Index,
+    len: usize,
+    marker: PhantomData<&'a mut [Box<[Slot<T>]>]>,
+}
+
+// SAFETY: The iterator behaves like a `&mut SlabList<T>`.
+#[allow(unsafe_code)]
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+
+// SAFETY: Same as above.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> IterMut<'a, T> {
+    /// Returns the node at the given index.
+    ///
+    /// # Safety
+    ///
+    /// The index must be an element of the list, and the node must not have
+    /// been returned before.
+    #[allow(unsafe_code)]
+    unsafe fn node(&mut self, index: Index) -> &'a mut Node<T> {
+        let (chunk, offset) = split(index);
+        // SAFETY: The caller guarantees that the index points to an occupied
+        // slot inside of the slab, which is borrowed mutably for 'a, and that
+        // no other reference to the node is alive. Only the slot is borrowed,
+        // not the rest of its chunk.
+        match unsafe { &mut (**self.chunks.add(chunk))[offset] } {
+            Slot::Occupied(node) => node,
+            Slot::Vacant(_) => unreachable!("list links to a free slot"),
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[allow(unsafe_code)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: Some elements remain, so the head is an element of the
+        // list. Each node is returned once, since the front and the back of
+        // the iterator stop when they meet.
+        let node = unsafe { self.node(self.head) };
+        self.len -= 1;
+        self.head = node.next;
+        Some(&mut node.data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    #[allow(unsafe_code)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: Some elements remain, so the tail is an element of the
+        // list. Each node is returned once, since the front and the back of
+        // the iterator stop when they meet.
+        let node = unsafe { self.node(self.tail) };
+        self.len -= 1;
+        self.tail = node.prev;
+        Some(&mut node.data)
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// An owning iterator over the elements of a SlabList.
+///
+/// This struct is created by the [`into_iter`] method on SlabList (provided
+/// by the [`IntoIterator`] trait). See its documentation for more.
+///
+/// [`into_iter`]: crate::collections::SlabList#into_iter;
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    list: SlabList<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.list.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for SlabList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    /// Consumes the list into an iterator yielding elements by value.
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SlabList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut SlabList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::DoublyLinkedList;
+    use std::collections::VecDeque;
+    use std::hint::black_box;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    /// Returns the elements of the list, after checking that the links of the
+    /// list agree in both directions, and that the free list is acyclic and
+    /// goes through every vacant slot of the slab.
+    fn check_slab<T: Clone>(list: &SlabList<T>) -> Vec<T> {
+        let slots: Vec<&Slot<T>> = list.chunks.iter().flat_map(|chunk| chunk.iter()).collect();
+        assert_eq!(slots.len(), list.capacity());
+
+        let mut elements = Vec::new();
+        let (mut prev, mut index) = (NIL, list.head);
+        while index != NIL {
+            assert!(
+                elements.len() < list.len(),
+                "list is longer than its length"
+            );
+            match slots[index as usize] {
+                Slot::Occupied(node) => {
+                    assert_eq!(node.prev, prev);
+                    elements.push(node.data.clone());
+                    (prev, index) = (index, node.next);
+                }
+                Slot::Vacant(_) => panic!("list links to the free slot {index}"),
+            }
+        }
+        assert_eq!(list.tail, prev);
+        assert_eq!(elements.len(), list.len());
+
+        let mut on_free_list = vec![false; slots.len()];
+        let mut index = list.free;
+        while index != NIL {
+            let seen = std::mem::replace(&mut on_free_list[index as usize], true);
+            assert!(!seen, "free list goes through the slot {index} twice");
+            match slots[index as usize] {
+                Slot::Vacant(next) => index = *next,
+                Slot::Occupied(_) => panic!("free list links to the occupied slot {index}"),
+            }
+        }
+        let vacant: Vec<bool> = slots
+            .iter()
+            .map(|slot| matches!(slot, Slot::Vacant(_)))
+            .collect();
+        assert_eq!(on_free_list, vacant);
+        assert_eq!(
+            vacant.iter().filter(|&&vacant| vacant).count(),
+            list.capacity() - list.len()
+        );
+        elements
+    }
+
+    #[test]
+    fn push_and_pop_at_both_ends() {
+        let mut list = SlabList::new();
+        assert_eq!((list.pop_front(), list.pop_back()), (None, None));
+        list.push_back(1);
+        list.push_front(0);
+        list.push_back(2);
+        assert_eq!(check_slab(&list), [0, 1, 2]);
+        assert_eq!((list.front(), list.back()), (Some(&0), Some(&2)));
+
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(check_slab(&list), [1]);
+        assert_eq!(list.pop_back(), Some(1));
+        assert!(list.is_empty());
+        assert_eq!((list.front(), list.back()), (None, None));
+
+        list.push_front(3);
+        *list.back_mut().unwrap() += 1;
+        assert_eq!(check_slab(&list), [4]);
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let mut list = SlabList::new();
+        list.extend(0..4);
+        assert_eq!(list.capacity(), CHUNK_LEN);
+        for i in 4..1000 {
+            if i % 2 == 0 {
+                list.pop_front();
+                list.push_back(i);
+            } else {
+                list.pop_back();
+                list.push_front(i);
+            }
+        }
+        assert_eq!(list.chunks.len(), 1);
+        assert_eq!(check_slab(&list), [999, 1, 2, 3]);
+
+        list.pop_front();
+        list.pop_front();
+        list.reserve(CHUNK_LEN - 2);
+        assert_eq!(list.capacity(), CHUNK_LEN);
+        list.reserve(CHUNK_LEN - 1);
+        assert_eq!(list.capacity(), 2 * CHUNK_LEN);
+        assert_eq!(check_slab(&list), [2, 3]);
+        list.clear();
+        assert!(check_slab(&list).is_empty());
+        assert_eq!(list.capacity(), 2 * CHUNK_LEN);
+    }
+
+    #[test]
+    fn interleaved_pushes_and_pops_keep_the_free_list_whole() {
+        let mut list = SlabList::new();
+        let mut model = VecDeque::new();
+        // A linear congruential generator, so that the runs are reproducible.
+        let mut state = 1u32;
+        for i in 0..3000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            // Pushes win slightly more often than pops until the list spans a
+            // few chunks, and then pops take over.
+            let push = (state >> 16) % 16 < if i < 2000 { 9 } else { 6 };
+            match (state & (1 << 8) == 0, push) {
+                (true, true) => {
+                    list.push_front(i);
+                    model.push_front(i);
+                }
+                (false, true) => {
+                    list.push_back(i);
+                    model.push_back(i);
+                }
+                (true, false) => assert_eq!(list.pop_front(), model.pop_front()),
+                (false, false) => assert_eq!(list.pop_back(), model.pop_back()),
+            }
+            if i % 500 == 0 {
+                list.reserve(CHUNK_LEN);
+            }
+            assert!(check_slab(&list).iter().eq(model.iter()));
+        }
+        assert!(list.chunks.len() > 2);
+    }
+
+    #[test]
+    fn growing_keeps_nodes_in_place() {
+        let mut list = SlabList::new();
+        list.push_back(String::from("front"));
+        let front: *const String = list.front().unwrap();
+        for i in 0..4 * CHUNK_LEN {
+            list.push_back(i.to_string());
+        }
+        list.reserve(1000);
+        assert!(std::ptr::eq(front, list.front().unwrap()));
+        assert_eq!(list.len(), 4 * CHUNK_LEN + 1);
+    }
+
+    #[test]
+    fn iterators_follow_the_links_across_chunks() {
+        // Alternating ends makes the order of the list differ from the order
+        // of the slots in the slab.
+        let mut list = SlabList::new();
+        for i in 0..2 * CHUNK_LEN + 3 {
+            if i % 2 == 0 {
+                list.push_back(i);
+            } else {
+                list.push_front(i);
+            }
+        }
+        let expected = check_slab(&list);
+        assert!(list.iter().eq(&expected));
+        assert!(list.iter().rev().eq(expected.iter().rev()));
+        assert_eq!(list.iter().len(), expected.len());
+
+        let mut iter = list.iter_mut();
+        while let (Some(front), Some(back)) = (iter.next(), iter.next_back()) {
+            std::mem::swap(front, back);
+        }
+        assert!(check_slab(&list).iter().eq(expected.iter().rev()));
+        assert_eq!(
+            format!("{list:?}"),
+            format!("{:?}", expected.iter().rev().collect::<Vec<_>>())
+        );
+        assert_eq!(list.clone(), list);
+        assert!(list.into_iter().eq(expected.into_iter().rev()));
+    }
+
+    #[test]
+    fn elements_are_dropped_with_the_list() {
+        let rc = Rc::new(());
+        let mut list = SlabList::new();
+        for _ in 0..4 {
+            list.push_back(rc.clone());
+        }
+        drop(list.pop_front());
+        assert_eq!(Rc::strong_count(&rc), 4);
+        let mut iter = list.clone().into_iter();
+        drop(iter.next_back());
+        assert_eq!(Rc::strong_count(&rc), 6);
+        drop(iter);
+        assert_eq!(Rc::strong_count(&rc), 4);
+        list.clear();
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    #[ignore = "Benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn throughput_against_boxed_list_and_vec_deque() {
+        const N_ITEMS: usize = 1_000;
+        const N_ROUNDS: usize = 10_000;
+
+        fn report(name: &str, elapsed: Duration) {
+            let ops = (N_ITEMS * N_ROUNDS) as f64 / elapsed.as_secs_f64();
+            println!("{name}: {elapsed:?} ({:.1} Mops/s)", ops / 1e6);
+        }
+
+        /// Runs the benchmark on a queue of `N_ITEMS` elements, where each
+        /// round pops all elements from the front, pushes them back at the
+        /// back, and then sums them by traversing the queue.
+        macro_rules! bench {
+            ($name:expr, $queue:expr) => {{
+                let mut queue = $queue;
+                queue.extend(0..N_ITEMS);
+                let start = Instant::now();
+                let mut sum = 0;
+                for _ in 0..N_ROUNDS {
+                    for _ in 0..N_ITEMS {
+                        let v = queue.pop_front().unwrap();
+                        queue.push_back(black_box(v));
+                    }
+                    sum += queue.iter().sum::<usize>();
+                }
+                report($name, start.elapsed());
+                assert_eq!(sum, N_ROUNDS * N_ITEMS * (N_ITEMS - 1) / 2);
+            }};
+        }
+
+        bench!("DoublyLinkedList", DoublyLinkedList::new());
+        bench!("SlabList", SlabList::new());
+        bench!("VecDeque", VecDeque::new());
+    }
+}
@@ -1,15 +1,21 @@
 //! Structure that enables single-threaded access to owned data.
 
 use crate::cell::Cell;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
 use std::ops::Deref;
 use std::ptr::NonNull;
 
 /// The inner representation of `Rc<T>` that gets allocated on the heap.
 struct RcInner<T> {
-    /// The value referenced by our smart pointer.
-    value: T,
-    /// The number of references that have been handed out.
-    refcount: Cell<usize>,
+    /// The value referenced by our smart pointer. It is dropped as soon as the
+    /// last `Rc` goes away, even if some `Weak` still keeps the allocation.
+    value: ManuallyDrop<T>,
+    /// The number of `Rc` that have been handed out.
+    strong: Cell<usize>,
+    /// The number of `Weak` that have been handed out, plus one that is
+    /// collectively held by all the `Rc` for as long as there is one.
+    weak: Cell<usize>,
 }
 
 /// A reference-counted smart pointer that deallocates the inner value
@@ -17,6 +23,7 @@ struct RcInner<T> {
 #[derive(Debug)]
 pub struct Rc<T> {
     inner: NonNull<RcInner<T>>,
+    marker: PhantomData<RcInner<T>>,
 }
 
 impl<T> Rc<T> {
@@ -24,49 +31,222 @@ impl<T> Rc<T> {
     pub fn new(value: T) -> Self {
         // Put the inner value onto the heap and keep a raw pointer to that memory location.
         let inner = Box::new(RcInner {
-            value,
-            refcount: Cell::new(1),
+            value: ManuallyDrop::new(value),
+            strong: Cell::new(1),
+            weak: Cell::new(1),
         });
         Self {
             // SAFETY: `Box::into_raw` does not give a null pointer.
             inner: unsafe { NonNull::new_unchecked(Box::into_raw(inner)) },
+            marker: PhantomData,
         }
     }
+
+    /// Create a `Weak` pointer to the value of this `Rc`.
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let inner = this.inner();
+        inner.weak.set(inner.weak.get() + 1);
+        Weak { inner: this.inner }
+    }
+
+    /// Get the number of `Rc` pointing to the value of this `Rc`.
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    /// Get the number of `Weak` pointing to the value of this `Rc`.
+    pub fn weak_count(this: &Self) -> usize {
+        this.inner().weak.get() - 1
+    }
+
+    fn inner(&self) -> &RcInner<T> {
+        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Rc`
+        // and the last `Weak` go away, dereference the shared poninter here is fine since we are
+        // having an `Rc`.
+        unsafe { self.inner.as_ref() }
+    }
 }
 
 impl<T> Deref for Rc<T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Rc`
-        // goes away, dereference the shared poninter here is fine since we are having an `Rc`.
-        let inner = unsafe { self.inner.as_ref() };
-        &inner.value
+        // The value is only dropped once the last `Rc` goes away.
+        &self.inner().value
     }
 }
 
 impl<T> Clone for Rc<T> {
     fn clone(&self) -> Self {
-        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Rc`
-        // goes away, dereference the shared poninter here is fine since we are having an `Rc`.
-        let inner = unsafe { self.inner.as_ref() };
-        inner.refcount.set(inner.refcount.get() + 1);
-        Rc { inner: self.inner }
+        let inner = self.inner();
+        inner.strong.set(inner.strong.get() + 1);
+        Rc {
+            inner: self.inner,
+            marker: PhantomData,
+        }
     }
 }
 
 impl<T> Drop for Rc<T> {
     fn drop(&mut self) {
-        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Rc`
-        // goes away, dereference the shared poninter here is fine since we are having an `Rc`.
-        let inner = unsafe { self.inner.as_ref() };
-        let refcount = inner.refcount.get();
-        if refcount == 1 {
+        let inner = self.inner();
+        let strong = inner.strong.get();
+        inner.strong.set(strong - 1);
+        if strong == 1 {
             // SAFETY: We are dropping the only `Rc` left, after being dropped, there is no more
-            // reference to `T`. Hence, deallocating the heap memory is safe.
-            unsafe { Box::from_raw(self.inner.as_ptr()) };
+            // reference to `T`. Hence, dropping the value is safe. `Weak` can no longer be
+            // upgraded since the strong count has reached zero.
+            unsafe { ManuallyDrop::drop(&mut (*self.inner.as_ptr()).value) };
+            // Release the weak reference that is held by all the `Rc`, this deallocates the
+            // memory if there is no `Weak` left.
+            drop(Weak { inner: self.inner });
+        }
+    }
+}
+
+/// A non-owning pointer to the value of an `Rc`.
+///
+/// A `Weak` does not keep the value alive, it must be upgraded to an `Rc` before the value can be
+/// accessed, which fails if the value has already been dropped. It does keep the allocation of
+/// the `Rc` alive, so that the strong count can still be checked.
+#[derive(Debug)]
+pub struct Weak<T> {
+    inner: NonNull<RcInner<T>>,
+}
+
+impl<T> Weak<T> {
+    /// Attempt to get an `Rc` to the value, returns `None` if the value has already been dropped.
+    pub fn upgrade(&self) -> Option<Rc<T>> {
+        let inner = self.inner();
+        let strong = inner.strong.get();
+        if strong == 0 {
+            return None;
+        }
+        inner.strong.set(strong + 1);
+        Some(Rc {
+            inner: self.inner,
+            marker: PhantomData,
+        })
+    }
+
+    /// Get the number of `Rc` pointing to the value.
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.get()
+    }
+
+    /// Get the number of `Weak` pointing to the value, returns 0 if the value has already been
+    /// dropped.
+    pub fn weak_count(&self) -> usize {
+        let inner = self.inner();
+        if inner.strong.get() == 0 {
+            0
         } else {
-            inner.refcount.set(refcount - 1);
+            inner.weak.get() - 1
         }
     }
+
+    fn inner(&self) -> &RcInner<T> {
+        // SAFETY: self.inner is a raw pointer to a `Box` that is deallocated when the last `Rc`
+        // and the last `Weak` go away, dereference the shared poninter here is fine since we are
+        // having a `Weak`.
+        unsafe { self.inner.as_ref() }
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        let inner = self.inner();
+        inner.weak.set(inner.weak.get() + 1);
+        Weak { inner: self.inner }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        let inner = self.inner();
+        let weak = inner.weak.get();
+        inner.weak.set(weak - 1);
+        if weak == 1 {
+            // SAFETY: We are dropping the last weak reference, which is only possible once every
+            // `Rc` has gone away and the value has been dropped. Hence, deallocating the heap
+            // memory is safe. The value is wrapped in `ManuallyDrop` so it is not dropped twice.
+            drop(unsafe { Box::from_raw(self.inner.as_ptr()) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Increment the shared counter when dropped.
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn counts_track_clones_and_drops() {
+        let rc = Rc::new(42);
+        assert_eq!(Rc::strong_count(&rc), 1);
+        assert_eq!(Rc::weak_count(&rc), 0);
+
+        let rc2 = rc.clone();
+        let weak = Rc::downgrade(&rc);
+        let weak2 = weak.clone();
+        assert_eq!(Rc::strong_count(&rc), 2);
+        assert_eq!(Rc::weak_count(&rc), 2);
+        assert_eq!(weak.strong_count(), 2);
+        assert_eq!(weak.weak_count(), 2);
+
+        drop(rc2);
+        drop(weak2);
+        assert_eq!(Rc::strong_count(&rc), 1);
+        assert_eq!(Rc::weak_count(&rc), 1);
+    }
+
+    #[test]
+    fn upgrade_fails_once_value_is_dropped() {
+        let drops = Cell::new(0);
+        let rc = Rc::new(DropCounter(&drops));
+        let weak = Rc::downgrade(&rc);
+
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(upgraded);
+        drop(rc);
+
+        // The value is dropped with the last `Rc` even though a `Weak` is alive.
+        assert_eq!(drops.get(), 1);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.strong_count(), 0);
+        assert_eq!(weak.weak_count(), 0);
+
+        drop(weak);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn value_holding_weak_to_itself() {
+        struct Node<'a> {
+            this: crate::refcell::RefCell<Option<Weak<Node<'a>>>>,
+            _drop: DropCounter<'a>,
+        }
+
+        let drops = Cell::new(0);
+        let rc = Rc::new(Node {
+            this: crate::refcell::RefCell::new(None),
+            _drop: DropCounter(&drops),
+        });
+        *rc.this.borrow_mut() = Some(Rc::downgrade(&rc));
+        assert_eq!(Rc::weak_count(&rc), 1);
+
+        // Dropping the value also drops the last `Weak`, which must not free the allocation
+        // while the `Rc` is still being dropped.
+        drop(rc);
+        assert_eq!(drops.get(), 1);
+    }
 }
@@ -3,15 +3,23 @@
 use crate::cell::Cell;
 use std::{
     cell::UnsafeCell,
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    mem,
     ops::{Deref, DerefMut},
+    ptr::NonNull,
 };
 
 /// The state of the reference cell. This is used for checking if a borrow is possible.
 #[derive(Debug, Clone, Copy)]
 enum RefState {
     Unshared,
+    /// The number of `Ref` that have been given out.
     Shared(usize),
-    Exclusive,
+    /// The number of `RefMut` that have been given out. There can be more than one after
+    /// [`RefMut::map_split`], each of them pointing to a disjoint part of the value.
+    Exclusive(usize),
 }
 
 /// A smart pointer that ensures the borrow checker semantics at runtime.
@@ -30,106 +38,309 @@ impl<T> RefCell<T> {
         }
     }
 
+    /// Borrow the inner value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if exclusive access has been given out. For a non-panicking variant, use
+    /// [`try_borrow`](RefCell::try_borrow).
+    #[track_caller]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        match self.try_borrow() {
+            Ok(r) => r,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
     /// Borrow the inner value if no exclusive access has been given out.
-    pub fn borrow(&self) -> Option<Ref<'_, T>> {
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
         match self.state.get() {
-            RefState::Unshared => {
-                self.state.set(RefState::Shared(1));
-                Some(Ref { refcell: self })
-            }
-            RefState::Shared(n) => {
-                self.state.set(RefState::Shared(n + 1));
-                Some(Ref { refcell: self })
-            }
-            RefState::Exclusive => None,
+            RefState::Unshared => self.state.set(RefState::Shared(1)),
+            RefState::Shared(n) => self.state.set(RefState::Shared(n + 1)),
+            RefState::Exclusive(_) => return Err(BorrowError { _private: () }),
+        }
+        Ok(Ref {
+            // SAFETY: `UnsafeCell::get` does not give a null pointer.
+            value: unsafe { NonNull::new_unchecked(self.value.get()) },
+            state: &self.state,
+            marker: PhantomData,
+        })
+    }
+
+    /// Take exclusive access to the inner value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value has been borrowed. For a non-panicking variant, use
+    /// [`try_borrow_mut`](RefCell::try_borrow_mut).
+    #[track_caller]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        match self.try_borrow_mut() {
+            Ok(r) => r,
+            Err(err) => panic!("{}", err),
         }
     }
 
     /// Take exclusive access to the inner value if it hasn't been borrowed.
-    pub fn borrow_mut(&self) -> Option<RefMut<'_, T>> {
-        if let RefState::Unshared = self.state.get() {
-            self.state.set(RefState::Exclusive);
-            Some(RefMut { refcell: self })
-        } else {
-            None
+    pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+        match self.state.get() {
+            RefState::Unshared => self.state.set(RefState::Exclusive(1)),
+            state => return Err(BorrowMutError { state }),
         }
+        Ok(RefMut {
+            // SAFETY: `UnsafeCell::get` does not give a null pointer.
+            value: unsafe { NonNull::new_unchecked(self.value.get()) },
+            state: &self.state,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// An error returned by [`RefCell::try_borrow`] when the value is exclusively borrowed.
+#[derive(Debug)]
+pub struct BorrowError {
+    _private: (),
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("already mutably borrowed")
     }
 }
 
-/// A shared reference to a `RefCell`.
+impl Error for BorrowError {}
+
+/// An error returned by [`RefCell::try_borrow_mut`] when the value is already borrowed.
 #[derive(Debug)]
-pub struct Ref<'refcell, T> {
-    refcell: &'refcell RefCell<T>,
+pub struct BorrowMutError {
+    /// The state of the cell at the time of the failed borrow.
+    state: RefState,
+}
+
+impl fmt::Display for BorrowMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.state {
+            RefState::Shared(1) => f.write_str("already borrowed: 1 shared borrow is alive"),
+            RefState::Shared(n) => write!(f, "already borrowed: {} shared borrows are alive", n),
+            RefState::Exclusive(_) => f.write_str("already mutably borrowed"),
+            RefState::Unshared => unreachable!(),
+        }
+    }
+}
+
+impl Error for BorrowMutError {}
+
+/// A shared reference to the value of a `RefCell`, or to a part of it.
+pub struct Ref<'b, T: ?Sized> {
+    /// Points into the value of the `RefCell`, which might be projected by [`Ref::map`].
+    value: NonNull<T>,
+    state: &'b Cell<RefState>,
+    marker: PhantomData<&'b T>,
+}
+
+impl<'b, T: ?Sized> Ref<'b, T> {
+    /// Make a new `Ref` to the same value. The `RefCell` stays borrowed until every copy goes
+    /// away.
+    ///
+    /// This is an associated function so that it does not shadow a `clone` method of `T`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn clone(orig: &Ref<'b, T>) -> Ref<'b, T> {
+        match orig.state.get() {
+            RefState::Shared(n) => orig.state.set(RefState::Shared(n + 1)),
+            RefState::Exclusive(_) | RefState::Unshared => unreachable!(),
+        }
+        Ref {
+            value: orig.value,
+            state: orig.state,
+            marker: PhantomData,
+        }
+    }
+
+    /// Make a new `Ref` to a part of the borrowed value, e.g. a field of a struct. The
+    /// `RefCell` stays borrowed for as long as the returned `Ref` is alive.
+    pub fn map<U: ?Sized, F>(orig: Ref<'b, T>, f: F) -> Ref<'b, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        let value = NonNull::from(f(&*orig));
+        Ref::project(orig, value)
+    }
+
+    /// Make a new `Ref` to an optional part of the borrowed value. The original `Ref` is given
+    /// back if the closure returns `None`.
+    pub fn filter_map<U: ?Sized, F>(orig: Ref<'b, T>, f: F) -> Result<Ref<'b, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        match f(&*orig).map(NonNull::from) {
+            Some(value) => Ok(Ref::project(orig, value)),
+            None => Err(orig),
+        }
+    }
+
+    /// Hand the borrow held by `orig` over to a new `Ref` pointing to `value`.
+    fn project<U: ?Sized>(orig: Ref<'b, T>, value: NonNull<U>) -> Ref<'b, U> {
+        let state = orig.state;
+        // The borrow is not released, it is now held by the returned `Ref`.
+        mem::forget(orig);
+        Ref {
+            value,
+            state,
+            marker: PhantomData,
+        }
+    }
 }
 
-impl<T> Drop for Ref<'_, T> {
+impl<T: ?Sized> Drop for Ref<'_, T> {
     fn drop(&mut self) {
-        match self.refcell.state.get() {
-            RefState::Shared(1) => self.refcell.state.set(RefState::Unshared),
-            RefState::Shared(n) => self.refcell.state.set(RefState::Shared(n - 1)),
-            RefState::Exclusive | RefState::Unshared => unreachable!(),
+        match self.state.get() {
+            RefState::Shared(1) => self.state.set(RefState::Unshared),
+            RefState::Shared(n) => self.state.set(RefState::Shared(n - 1)),
+            RefState::Exclusive(_) | RefState::Unshared => unreachable!(),
         }
     }
 }
 
-impl<T> Deref for Ref<'_, T> {
+impl<T: ?Sized> Deref for Ref<'_, T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
         // SAFETY: A `Ref` only exists if no exclusive access to the inner value
         // has been given out. Hence, dereferencing into a immutable reference is ok.
-        unsafe { &*self.refcell.value.get() }
+        unsafe { self.value.as_ref() }
     }
 }
 
-/// An exclusive reference to a `RefCell`.
-#[derive(Debug)]
-pub struct RefMut<'refcell, T> {
-    refcell: &'refcell RefCell<T>,
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Ref<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// An exclusive reference to the value of a `RefCell`, or to a part of it.
+pub struct RefMut<'b, T: ?Sized> {
+    /// Points into the value of the `RefCell`, which might be projected by [`RefMut::map`].
+    value: NonNull<T>,
+    state: &'b Cell<RefState>,
+    /// Makes `RefMut` invariant over `T`, like `&mut T`.
+    marker: PhantomData<&'b mut T>,
+}
+
+impl<'b, T: ?Sized> RefMut<'b, T> {
+    /// Make a new `RefMut` to a part of the borrowed value, e.g. a field of a struct. The
+    /// `RefCell` stays exclusively borrowed for as long as the returned `RefMut` is alive.
+    pub fn map<U: ?Sized, F>(mut orig: RefMut<'b, T>, f: F) -> RefMut<'b, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let value = NonNull::from(f(&mut *orig));
+        RefMut::project(orig, value)
+    }
+
+    /// Make a new `RefMut` to an optional part of the borrowed value. The original `RefMut` is
+    /// given back if the closure returns `None`.
+    pub fn filter_map<U: ?Sized, F>(mut orig: RefMut<'b, T>, f: F) -> Result<RefMut<'b, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        match f(&mut *orig).map(NonNull::from) {
+            Some(value) => Ok(RefMut::project(orig, value)),
+            None => Err(orig),
+        }
+    }
+
+    /// Split a `RefMut` into two `RefMut` to disjoint parts of the borrowed value, e.g. two
+    /// halves of a slice. The `RefCell` stays exclusively borrowed until both go away.
+    pub fn map_split<U: ?Sized, V: ?Sized, F>(
+        mut orig: RefMut<'b, T>,
+        f: F,
+    ) -> (RefMut<'b, U>, RefMut<'b, V>)
+    where
+        F: FnOnce(&mut T) -> (&mut U, &mut V),
+    {
+        let (a, b) = f(&mut *orig);
+        let (a, b) = (NonNull::from(a), NonNull::from(b));
+        match orig.state.get() {
+            RefState::Exclusive(n) => orig.state.set(RefState::Exclusive(n + 1)),
+            RefState::Shared(_) | RefState::Unshared => unreachable!(),
+        }
+        let state = orig.state;
+        (
+            RefMut::project(orig, a),
+            RefMut {
+                value: b,
+                state,
+                marker: PhantomData,
+            },
+        )
+    }
+
+    /// Hand the borrow held by `orig` over to a new `RefMut` pointing to `value`.
+    fn project<U: ?Sized>(orig: RefMut<'b, T>, value: NonNull<U>) -> RefMut<'b, U> {
+        let state = orig.state;
+        // The borrow is not released, it is now held by the returned `RefMut`.
+        mem::forget(orig);
+        RefMut {
+            value,
+            state,
+            marker: PhantomData,
+        }
+    }
 }
 
-impl<T> Drop for RefMut<'_, T> {
+impl<T: ?Sized> Drop for RefMut<'_, T> {
     fn drop(&mut self) {
-        match self.refcell.state.get() {
-            RefState::Exclusive => self.refcell.state.set(RefState::Unshared),
+        match self.state.get() {
+            RefState::Exclusive(1) => self.state.set(RefState::Unshared),
+            RefState::Exclusive(n) => self.state.set(RefState::Exclusive(n - 1)),
             RefState::Shared(_) | RefState::Unshared => unreachable!(),
         }
     }
 }
 
-impl<T> Deref for RefMut<'_, T> {
+impl<T: ?Sized> Deref for RefMut<'_, T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
         // SAFETY: A `RefMut` only exists if no reference to the inner value
-        // has been given out. Hence, dereferencing into a immutable reference is ok.
-        unsafe { &*self.refcell.value.get() }
+        // has been given out. Other `RefMut` created by `map_split` point to
+        // disjoint parts of the value. Hence, dereferencing into a immutable
+        // reference is ok.
+        unsafe { self.value.as_ref() }
     }
 }
 
-impl<T> DerefMut for RefMut<'_, T> {
+impl<T: ?Sized> DerefMut for RefMut<'_, T> {
     fn deref_mut(&mut self) -> &mut <Self as Deref>::Target {
         // SAFETY: A `RefMut` only exists if no reference to the inner value
-        // has been given out. Hence, dereferencing into a mutable reference is ok.
-        unsafe { &mut *self.refcell.value.get() }
+        // has been given out. Other `RefMut` created by `map_split` point to
+        // disjoint parts of the value. Hence, dereferencing into a mutable
+        // reference is ok.
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::RefCell;
+    use super::{Ref, RefCell, RefMut};
 
     #[test]
-    fn borrow_mut_returns_none_when_borrow_is_alive() {
+    fn try_borrow_mut_fails_when_borrow_is_alive() {
         let cell = RefCell::new("test");
-        let c1 = cell.borrow();
-        let c2 = cell.borrow_mut();
-        assert!(matches!(c1, Some(_)));
-        assert!(matches!(c2, None));
+        let c1 = cell.try_borrow();
+        let c2 = cell.try_borrow_mut();
+        assert!(c1.is_ok());
+        assert!(c2.is_err());
     }
 
     #[test]
     fn borrow_mut_points_to_valid_data() {
         let cell = RefCell::new("test");
-        let c = cell.borrow_mut().unwrap();
+        let c = cell.borrow_mut();
         assert_eq!("test", *c);
     }
 
@@ -137,27 +348,123 @@ mod tests {
     fn borrow_mut_can_mutate_referenced_data() {
         let cell = RefCell::new("test");
         {
-            let mut c = cell.borrow_mut().unwrap();
+            let mut c = cell.borrow_mut();
             assert_eq!("test", *c);
             *c = "hello";
         }
-        let c = cell.borrow().unwrap();
+        let c = cell.borrow();
         assert_eq!("hello", *c);
     }
 
     #[test]
-    fn borrow_returns_none_when_borrow_mut_is_alive() {
+    fn try_borrow_fails_when_borrow_mut_is_alive() {
         let cell = RefCell::new("test");
-        let c1 = cell.borrow_mut();
-        let c2 = cell.borrow();
-        assert!(matches!(c1, Some(_)));
-        assert!(matches!(c2, None));
+        let c1 = cell.try_borrow_mut();
+        let c2 = cell.try_borrow();
+        assert!(c1.is_ok());
+        assert!(c2.is_err());
     }
 
     #[test]
     fn borrow_points_to_valid_data() {
         let cell = RefCell::new("test");
-        let c = cell.borrow().unwrap();
+        let c = cell.borrow();
         assert_eq!("test", *c);
     }
+
+    #[test]
+    fn errors_report_conflicting_borrows() {
+        let cell = RefCell::new("test");
+        {
+            let _c1 = cell.borrow();
+            let _c2 = cell.borrow();
+            let err = cell.try_borrow_mut().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "already borrowed: 2 shared borrows are alive"
+            );
+        }
+        let _c = cell.borrow_mut();
+        assert_eq!(
+            cell.try_borrow_mut().unwrap_err().to_string(),
+            "already mutably borrowed"
+        );
+        assert_eq!(
+            cell.try_borrow().unwrap_err().to_string(),
+            "already mutably borrowed"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed: 1 shared borrow is alive")]
+    fn borrow_mut_panics_when_borrow_is_alive() {
+        let cell = RefCell::new("test");
+        let _c1 = cell.borrow();
+        let _c2 = cell.borrow_mut();
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn borrow_panics_when_borrow_mut_is_alive() {
+        let cell = RefCell::new("test");
+        let _c1 = cell.borrow_mut();
+        let _c2 = cell.borrow();
+    }
+
+    #[test]
+    fn mapped_ref_keeps_the_cell_borrowed() {
+        let cell = RefCell::new((1, String::from("test")));
+        let r = Ref::map(cell.borrow(), |(_, s)| s.as_str());
+        assert_eq!("test", &*r);
+
+        let r2 = Ref::clone(&r);
+        drop(r);
+        assert!(cell.try_borrow_mut().is_err());
+        drop(r2);
+        assert!(cell.try_borrow_mut().is_ok());
+    }
+
+    #[test]
+    fn filter_map_gives_back_the_original_borrow() {
+        let cell = RefCell::new(vec![1, 2, 3]);
+        let r = Ref::filter_map(cell.borrow(), |v| v.get(1)).unwrap();
+        assert_eq!(2, *r);
+        drop(r);
+
+        let r = Ref::filter_map(cell.borrow(), |v| v.get(3)).unwrap_err();
+        assert_eq!(&[1, 2, 3], &r[..]);
+        drop(r);
+
+        let mut m = RefMut::filter_map(cell.borrow_mut(), |v| v.last_mut()).unwrap();
+        *m = 4;
+        assert!(cell.try_borrow().is_err());
+        drop(m);
+        assert_eq!(&[1, 2, 4], &cell.borrow()[..]);
+    }
+
+    #[test]
+    fn mapped_ref_mut_can_mutate_a_part_of_the_value() {
+        let cell = RefCell::new((1, 2));
+        {
+            let mut m = RefMut::map(cell.borrow_mut(), |(_, b)| b);
+            *m += 40;
+            assert!(cell.try_borrow().is_err());
+        }
+        assert_eq!((1, 42), *cell.borrow());
+    }
+
+    #[test]
+    fn map_split_holds_the_borrow_until_both_halves_are_dropped() {
+        let cell = RefCell::new([1, 2, 3, 4]);
+        let (mut a, mut b) = RefMut::map_split(cell.borrow_mut(), |v| v.split_at_mut(2));
+        a[0] = 10;
+        b[1] = 40;
+        drop(a);
+        assert_eq!(
+            cell.try_borrow().unwrap_err().to_string(),
+            "already mutably borrowed"
+        );
+        drop(b);
+        assert_eq!([10, 2, 3, 40], *cell.borrow());
+    }
 }